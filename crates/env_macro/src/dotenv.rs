@@ -0,0 +1,274 @@
+// SPDX-License-Identifier: MIT
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, PoisonError};
+
+/// Name of the file variables are loaded from.
+const DOTENV_FILE_NAME: &str = ".env";
+
+/// Variables of a `.env` file, or the reason it could not be read or parsed.
+pub(crate) type DotenvVars = Result<HashMap<String, String>, String>;
+
+/// Returns the variables of the closest `.env` file,
+/// or the error describing why it could not be parsed, including its path.
+/// The file is read and parsed only once per process, even if several expansions run concurrently,
+/// so changes made to the file later in the build are not observed.
+pub(crate) fn closest_dotenv() -> &'static DotenvVars {
+    static DOTENV: OnceLock<DotenvVars> = OnceLock::new();
+
+    DOTENV.get_or_init(load_dotenv)
+}
+
+/// Loads the variables of the closest `.env` file.
+/// Returns an empty map if there is no `.env` file or it cannot be read, or an error if it is malformed.
+fn load_dotenv() -> DotenvVars {
+    let Some(path) = find_dotenv() else {
+        return Ok(HashMap::new());
+    };
+    let Ok(contents) = std::fs::read_to_string(&path) else {
+        return Ok(HashMap::new());
+    };
+    parse_dotenv(&contents).map_err(|err| format!("{}: {}", path.display(), err))
+}
+
+/// Reads the variables of the `.env` file at the given path.
+/// Each file is read and parsed only once per process, like the closest `.env` file,
+/// and a failure to read or parse it is cached as well.
+pub(crate) fn read_dotenv_file(path: &Path) -> DotenvVars {
+    static FILES: OnceLock<Mutex<HashMap<PathBuf, DotenvVars>>> = OnceLock::new();
+
+    // The lock is held while the file is read, so concurrent expansions don't read it twice.
+    let mut files = FILES
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(PoisonError::into_inner);
+    files
+        .entry(path.to_path_buf())
+        .or_insert_with_key(|path| {
+            let contents = std::fs::read_to_string(path).map_err(|err| err.to_string())?;
+            parse_dotenv(&contents)
+        })
+        .clone()
+}
+
+/// Finds the closest `.env` file walking up from the current directory.
+fn find_dotenv() -> Option<PathBuf> {
+    let current_dir = std::env::current_dir().ok()?;
+    current_dir
+        .ancestors()
+        .map(|dir| dir.join(DOTENV_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// Parses the contents of a `.env` file.
+/// Each line is a `KEY=VALUE` pair, blank lines and lines starting with `#` are ignored.
+/// Values can be wrapped in single or double quotes, which are removed.
+/// Unquoted values end at an inline comment, i.e. a `#` preceded by whitespace like in `PORT=8080 # primary`,
+/// while a `#` inside quotes is kept.
+/// Double-quoted values can span multiple lines, like PEM keys, and have their escapes like `\n` resolved.
+/// A byte order mark and Windows line endings are ignored.
+/// Returns an error with the line number if a double-quoted value is never terminated.
+fn parse_dotenv(contents: &str) -> Result<HashMap<String, String>, String> {
+    let contents = normalize_text(contents);
+    let mut vars = HashMap::new();
+    let mut lines = contents.lines().enumerate();
+    while let Some((index, line)) = lines.next() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let line = line.strip_prefix("export ").unwrap_or(line);
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        let key = key.trim();
+        // The untrimmed value is kept to tell an inline comment like `KEY= # comment` from a value like `KEY=#fff`.
+        let raw_value = value;
+        let value = value.trim();
+
+        let value = match value.strip_prefix('"') {
+            Some(quoted) => {
+                // The value continues on the following lines until the closing quote.
+                let mut quoted = quoted.to_string();
+                loop {
+                    if let Some(value) = parse_double_quoted(&quoted) {
+                        break value;
+                    }
+                    let Some((_, next_line)) = lines.next() else {
+                        return Err(format!(
+                            "unterminated quoted value of {} starting at line {}",
+                            key,
+                            index + 1
+                        ));
+                    };
+                    quoted.push('\n');
+                    quoted.push_str(next_line);
+                }
+            }
+            None => parse_unquoted(raw_value).to_string(),
+        };
+        vars.insert(key.to_string(), value);
+    }
+    Ok(vars)
+}
+
+/// Strips a leading UTF-8 byte order mark and converts `\r\n` and `\r` line endings to `\n`,
+/// so that files authored on Windows read the same as others.
+pub(crate) fn normalize_text(contents: &str) -> String {
+    contents
+        .strip_prefix('\u{feff}')
+        .unwrap_or(contents)
+        .replace("\r\n", "\n")
+        .replace('\r', "\n")
+}
+
+/// Parses the rest of a double-quoted value after the opening quote, resolving escapes.
+/// Anything after the closing quote, like a comment, is ignored.
+/// Returns `None` if the value has no closing quote.
+fn parse_double_quoted(quoted: &str) -> Option<String> {
+    let mut value = String::with_capacity(quoted.len());
+    let mut chars = quoted.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(value),
+            '\\' => match chars.next()? {
+                'n' => value.push('\n'),
+                'r' => value.push('\r'),
+                't' => value.push('\t'),
+                escaped @ ('"' | '\\') => value.push(escaped),
+                other => {
+                    value.push('\\');
+                    value.push(other);
+                }
+            },
+            _ => value.push(c),
+        }
+    }
+    None
+}
+
+/// Parses a value that is not double-quoted, given with the whitespace following the `=`.
+/// A single-quoted value is taken literally up to the closing quote, ignoring anything after it like a comment.
+/// Otherwise the value ends at an inline comment, i.e. a `#` preceded by whitespace, so `#ff0000` is kept as is.
+fn parse_unquoted(value: &str) -> &str {
+    if let Some((literal, _)) = value
+        .trim_start()
+        .strip_prefix('\'')
+        .and_then(|quoted| quoted.split_once('\''))
+    {
+        return literal;
+    }
+    match value
+        .char_indices()
+        .find(|&(index, c)| c == '#' && value[..index].ends_with(char::is_whitespace))
+    {
+        Some((index, _)) => value[..index].trim(),
+        None => value.trim(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Writes a `.env` file with the given contents to a fresh temporary directory and returns its path.
+    fn write_dotenv(name: &str, contents: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("env_macro_dotenv_{}_{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(DOTENV_FILE_NAME);
+        std::fs::write(&path, contents).unwrap();
+        path
+    }
+
+    #[test]
+    fn reads_key_value_lines() {
+        let path = write_dotenv(
+            "basic",
+            "# settings\n\nPORT=8080\n  NAME = app  \nexport MODE=dev\nnot a pair\n",
+        );
+        let vars = read_dotenv_file(&path).unwrap();
+        assert_eq!(vars.len(), 3);
+        assert_eq!(vars["PORT"], "8080");
+        assert_eq!(vars["NAME"], "app");
+        assert_eq!(vars["MODE"], "dev");
+    }
+
+    #[test]
+    fn reports_missing_file() {
+        let path = write_dotenv("missing", "").with_file_name("missing.env");
+        assert!(read_dotenv_file(&path).is_err());
+    }
+
+    #[test]
+    fn reads_multiline_quoted_values() {
+        let vars = parse_dotenv(
+            "KEY=\"-----BEGIN KEY-----\nabc\n-----END KEY-----\"\nESCAPED=\"a\\nb \\\"c\\\"\"\nNEXT=1\n",
+        )
+        .unwrap();
+        assert_eq!(vars["KEY"], "-----BEGIN KEY-----\nabc\n-----END KEY-----");
+        assert_eq!(vars["ESCAPED"], "a\nb \"c\"");
+        assert_eq!(vars["NEXT"], "1");
+    }
+
+    #[test]
+    fn reports_unterminated_quoted_value() {
+        assert_eq!(
+            parse_dotenv("PORT=1\nKEY=\"abc\nNEXT=1\n").unwrap_err(),
+            "unterminated quoted value of KEY starting at line 2"
+        );
+    }
+
+    #[test]
+    fn reads_file_once_across_threads() {
+        let path = write_dotenv("concurrent", "PORT=8080\n");
+        let results: Vec<DotenvVars> = std::thread::scope(|scope| {
+            let readers: Vec<_> = (0..8)
+                .map(|_| scope.spawn(|| read_dotenv_file(&path)))
+                .collect();
+            readers
+                .into_iter()
+                .map(|reader| reader.join().unwrap())
+                .collect()
+        });
+        // Later changes to the file are not observed.
+        std::fs::write(&path, "PORT=9090\n").unwrap();
+        for vars in results.iter().chain([&read_dotenv_file(&path)]) {
+            assert_eq!(vars.as_ref().unwrap()["PORT"], "8080");
+        }
+    }
+
+    #[test]
+    fn ignores_byte_order_mark_and_windows_line_endings() {
+        let path = write_dotenv(
+            "windows",
+            "\u{feff}PORT=8080\r\nKEY=\"a\r\nb\"\r\nOLD_MAC=1\rLAST=2",
+        );
+        let vars = read_dotenv_file(&path).unwrap();
+        assert_eq!(vars["PORT"], "8080");
+        assert_eq!(vars["KEY"], "a\nb");
+        assert_eq!(vars["OLD_MAC"], "1");
+        assert_eq!(vars["LAST"], "2");
+    }
+
+    #[test]
+    fn strips_inline_comments() {
+        let vars = parse_dotenv(concat!(
+            "# full-line comment\n",
+            "PORT=8080 # primary\n",
+            "COLOR=#ff0000\n",
+            "QUOTED=\"a # b\" # comment\n",
+            "SINGLE='c # d'\n",
+            "EMPTY= # nothing\n",
+        ))
+        .unwrap();
+        assert_eq!(vars.len(), 5);
+        assert_eq!(vars["PORT"], "8080");
+        assert_eq!(vars["COLOR"], "#ff0000");
+        assert_eq!(vars["QUOTED"], "a # b");
+        assert_eq!(vars["SINGLE"], "c # d");
+        assert_eq!(vars["EMPTY"], "");
+    }
+}
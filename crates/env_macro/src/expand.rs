@@ -0,0 +1,1392 @@
+// SPDX-License-Identifier: MIT
+// Based on the code from Alexandria library (https://github.com/keep-starknet-strange/alexandria)
+// Copyright (c) 2025 Alexandria Contributors
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use cairo_lang_macro::{Diagnostic, TokenStream};
+use cairo_lang_parser::utils::SimpleParserDatabase;
+use cairo_lang_syntax::node::ast::{Arg, ArgClause, Expr, MaybeModuleBody, ModuleItem};
+use cairo_lang_syntax::node::{Terminal, TypedSyntaxNode};
+use cairo_lang_utils::Upcast;
+use num_bigint::{BigInt, Sign};
+
+use crate::options::{
+    get_bool_default_value, get_bool_option, get_endian_option, get_env_variable_name,
+    get_env_variable_names, get_identifier_option, get_numeric_default_value, get_numeric_literal,
+    get_numeric_option, get_shortstring_default_value, get_string_array_option,
+    get_string_default_value, get_string_option, Endian, ValueKind,
+};
+use crate::resolve::check_felt_range;
+use crate::syntax::{
+    error_at, not_set_error, parse_attribute_args, parse_macro_arg_list, parse_macro_args,
+    parse_module_item,
+};
+use crate::value::{
+    is_bps_in_range, numeric_parse_error, parse_bps_value, parse_color_value, parse_duration_value,
+    parse_env_value, parse_env_value_as, parse_fixed_value, parse_numeric_value,
+    parse_semver_value, parse_socketaddr_value, parse_uuid_value, short_string_literal,
+    string_literal, truncate_value, EnvValue, MAX_BPS,
+};
+use crate::{lookup_env_var, package_path, Env};
+
+/// Expands the string environment variable macro.
+/// Returns the value of the environment variable as a `ByteArray` literal or a diagnostic error if the variable is not set or there were parsing errors.
+pub(crate) fn expand_env_str_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_str!", token_stream, &db)?;
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+
+    match lookup_env_var(env, &env_var_name)? {
+        Some(val) => Ok(TokenStream::new(string_literal(val.as_bytes()))),
+        None => {
+            if macro_args.len() == 2 {
+                get_string_default_value(&db, &macro_args[1].arg_clause(db.upcast()))
+            } else {
+                Err(not_set_error(
+                    &db,
+                    &macro_args[0],
+                    &env_var_name,
+                    Some(("env_str!", "\"\"")),
+                ))
+            }
+        }
+    }
+}
+
+/// Expands the optional environment variable macro.
+/// Returns `Option::Some` with the value of the environment variable or `Option::None` if it is not set,
+/// or a diagnostic error if there were parsing errors or a numeric value doesn't fit into `felt252`.
+pub(crate) fn expand_option_env_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("option_env!", token_stream, &db)?;
+
+    if macro_args.len() > 1 {
+        return Err(Diagnostic::error(
+            "option_env! accepts only the environment variable name",
+        ));
+    }
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+
+    match lookup_env_var(env, &env_var_name)? {
+        Some(val) => Ok(TokenStream::new(format!(
+            "Option::Some({})",
+            check_felt_range(&env_var_name, parse_env_value(&env_var_name, &val)?, false)?
+                .to_literal()
+        ))),
+        None => Ok(TokenStream::new("Option::None".to_string())),
+    }
+}
+
+/// Expands the boolean environment variable macro.
+/// Returns the value of the environment variable as a `bool` literal or a diagnostic error if the variable is not set or there were parsing errors.
+pub(crate) fn expand_env_bool_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_bool!", token_stream, &db)?;
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+
+    match lookup_env_var(env, &env_var_name)? {
+        Some(val) => {
+            let bool_val = parse_env_value_as(&env_var_name, &val, ValueKind::Bool)?;
+            Ok(TokenStream::new(bool_val.to_literal()))
+        }
+        None => {
+            if macro_args.len() == 2 {
+                get_bool_default_value(&db, &macro_args[1].arg_clause(db.upcast()))
+            } else {
+                Err(not_set_error(
+                    &db,
+                    &macro_args[0],
+                    &env_var_name,
+                    Some(("env_bool!", "false")),
+                ))
+            }
+        }
+    }
+}
+
+/// Expands the `u256` environment variable macro.
+/// Returns the value of the environment variable as a `u256` struct expression or a diagnostic error if the variable is not set or there were parsing errors.
+pub(crate) fn expand_env_u256_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_u256!", token_stream, &db)?;
+    if let Some(extra_arg) = macro_args.get(2) {
+        return Err(error_at(&db, extra_arg, "Unexpected argument"));
+    }
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+
+    let numeric_val = match lookup_env_var(env, &env_var_name)? {
+        Some(val) => parse_numeric_value(&env_var_name, val.trim())?
+            .ok_or_else(|| numeric_parse_error(&env_var_name, &val))?,
+        None => {
+            if macro_args.len() == 2 {
+                get_numeric_default_value(&db, &macro_args[1].arg_clause(db.upcast()))?
+            } else {
+                return Err(not_set_error(
+                    &db,
+                    &macro_args[0],
+                    &env_var_name,
+                    Some(("env_u256!", "0")),
+                ));
+            }
+        }
+    };
+
+    if numeric_val < BigInt::ZERO || numeric_val.bits() > 256 {
+        return Err(Diagnostic::error(format!(
+            "{}={} does not fit into u256",
+            env_var_name, numeric_val
+        )));
+    }
+
+    let low_mask = (BigInt::from(1) << 128) - 1;
+    Ok(TokenStream::new(format!(
+        "u256 {{ low: {}, high: {} }}",
+        &numeric_val & low_mask,
+        numeric_val >> 128
+    )))
+}
+
+/// Expands the short string environment variable macro.
+/// Returns the value of the environment variable as a `felt252` literal or a diagnostic error if the variable is not set or is not a valid short string.
+pub(crate) fn expand_env_shortstring_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_shortstring!", token_stream, &db)?;
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+    let mut default_arg = None;
+    let mut endian = Endian::Big;
+    let mut utf8 = false;
+    for arg in &macro_args[1..] {
+        let arg_clause = match arg.arg_clause(db.upcast()) {
+            ArgClause::Unnamed(_) if default_arg.is_none() => {
+                default_arg = Some(arg);
+                continue;
+            }
+            ArgClause::Named(arg_clause) => arg_clause,
+            _ => return Err(error_at(&db, arg, "Unexpected argument")),
+        };
+        let key = arg_clause.name(db.upcast()).text(db.upcast());
+        match key.as_str() {
+            "endian" => endian = get_endian_option(&db, arg)?,
+            "utf8" => utf8 = get_bool_option(&key, &arg_clause.value(db.upcast()))?,
+            _ => {
+                return Err(error_at(
+                    &db,
+                    &arg_clause,
+                    format!("Unexpected argument: {}", key),
+                ))
+            }
+        }
+    }
+
+    match lookup_env_var(env, &env_var_name)? {
+        Some(val) => {
+            if !utf8 && !val.is_ascii() {
+                return Err(Diagnostic::error(format!(
+                    "{}={} is not a valid short string: only ASCII characters are supported, pass utf8: true to pack UTF-8 bytes",
+                    env_var_name,
+                    truncate_value(&val)
+                )));
+            }
+            if val.len() > 31 {
+                let unit = if utf8 { "bytes" } else { "characters" };
+                return Err(Diagnostic::error(format!(
+                    "{}={} is not a valid short string: it is {} {} long, at most 31 are supported",
+                    env_var_name,
+                    truncate_value(&val),
+                    val.len(),
+                    unit
+                )));
+            }
+            Ok(TokenStream::new(endian.pack(val.as_bytes()).to_string()))
+        }
+        None => {
+            if let Some(default_arg) = default_arg {
+                get_shortstring_default_value(&db, &default_arg.arg_clause(db.upcast()), endian)
+            } else {
+                Err(not_set_error(
+                    &db,
+                    &macro_args[0],
+                    &env_var_name,
+                    Some(("env_shortstring!", "''")),
+                ))
+            }
+        }
+    }
+}
+
+/// Expands the array environment variable macro.
+/// Returns the value of the environment variable as an `array!` expression or a diagnostic error if the variable is not set or an element is not numeric.
+pub(crate) fn expand_env_array_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_array!", token_stream, &db)?;
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+    let separator = get_separator_option(&db, &macro_args[1..])?;
+
+    let val = lookup_env_var(env, &env_var_name)?
+        .ok_or_else(|| not_set_error(&db, &macro_args[0], &env_var_name, None))?;
+    let elements = parse_numeric_list(&env_var_name, &val, &separator)?;
+
+    Ok(TokenStream::new(format!("array![{}]", elements.join(", "))))
+}
+
+/// Expands the span environment variable macro.
+/// Returns the value of the environment variable as a `.span()` expression of a fixed-size array
+/// or a diagnostic error if the variable is not set or an element is not numeric.
+pub(crate) fn expand_env_span_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_span!", token_stream, &db)?;
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+    let separator = get_separator_option(&db, &macro_args[1..])?;
+
+    let val = lookup_env_var(env, &env_var_name)?
+        .ok_or_else(|| not_set_error(&db, &macro_args[0], &env_var_name, None))?;
+    let elements = parse_numeric_list(&env_var_name, &val, &separator)?;
+
+    Ok(TokenStream::new(format!(
+        "[{}].span()",
+        elements.join(", ")
+    )))
+}
+
+/// Expands the duration environment variable macro.
+/// Returns the duration in seconds as a numeric literal or a diagnostic error if the variable is not set or is not a valid duration.
+pub(crate) fn expand_env_duration_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_duration!", token_stream, &db)?;
+    if let Some(extra_arg) = macro_args.get(2) {
+        return Err(error_at(&db, extra_arg, "Unexpected argument"));
+    }
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+
+    let seconds = match lookup_env_var(env, &env_var_name)? {
+        Some(val) => parse_duration_value(&env_var_name, &val)?,
+        None => {
+            if macro_args.len() == 2 {
+                get_numeric_default_value(&db, &macro_args[1].arg_clause(db.upcast()))?
+            } else {
+                return Err(not_set_error(
+                    &db,
+                    &macro_args[0],
+                    &env_var_name,
+                    Some(("env_duration!", "0")),
+                ));
+            }
+        }
+    };
+
+    let env_value = check_felt_range(&env_var_name, EnvValue::Numeric(seconds), false)?;
+    Ok(TokenStream::new(env_value.to_literal()))
+}
+
+/// Expands the basis points environment variable macro.
+/// Returns the percentage in basis points as a numeric literal or a diagnostic error if the variable is not set or is not a valid percentage.
+pub(crate) fn expand_env_bps_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_bps!", token_stream, &db)?;
+    if let Some(extra_arg) = macro_args.get(2) {
+        return Err(error_at(&db, extra_arg, "Unexpected argument"));
+    }
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+
+    let bps = match lookup_env_var(env, &env_var_name)? {
+        Some(val) => parse_bps_value(&env_var_name, &val)?,
+        None => {
+            if macro_args.len() == 2 {
+                let bps = get_numeric_default_value(&db, &macro_args[1].arg_clause(db.upcast()))?;
+                if !is_bps_in_range(&bps) {
+                    return Err(error_at(
+                        &db,
+                        &macro_args[1],
+                        format!(
+                            "Default of {} is not between 0 and {} basis points: {}",
+                            env_var_name, MAX_BPS, bps
+                        ),
+                    ));
+                }
+                bps
+            } else {
+                return Err(not_set_error(
+                    &db,
+                    &macro_args[0],
+                    &env_var_name,
+                    Some(("env_bps!", "0")),
+                ));
+            }
+        }
+    };
+
+    Ok(TokenStream::new(bps.to_string()))
+}
+
+/// Expands the base64 bytes environment variable macro.
+/// Returns the decoded bytes as an `array!` expression or a `ByteArray` literal,
+/// or a diagnostic error if the variable is not set or is not valid base64.
+pub(crate) fn expand_env_bytes_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_bytes!", token_stream, &db)?;
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+    let as_byte_array = get_bytes_kind_option(&db, &macro_args[1..])?;
+
+    let val = lookup_env_var(env, &env_var_name)?
+        .ok_or_else(|| not_set_error(&db, &macro_args[0], &env_var_name, None))?;
+    let bytes = BASE64_STANDARD.decode(val.trim()).map_err(|err| {
+        error_at(
+            &db,
+            &macro_args[0],
+            format!(
+                "Failed to decode base64 value of environment variable {}: {}",
+                env_var_name, err
+            ),
+        )
+    })?;
+
+    if as_byte_array {
+        return Ok(TokenStream::new(string_literal(&bytes)));
+    }
+    let elements: Vec<String> = bytes.iter().map(|byte| format!("0x{:02x}", byte)).collect();
+    Ok(TokenStream::new(format!("array![{}]", elements.join(", "))))
+}
+
+/// Parses the arguments following the environment variable name of the bytes macro, which can only be `kind:`.
+/// Returns whether the bytes are expanded to a `ByteArray`, or a diagnostic error if an unexpected argument is given.
+fn get_bytes_kind_option(db: &SimpleParserDatabase, args: &[Arg]) -> Result<bool, Diagnostic> {
+    let mut as_byte_array = false;
+    for arg in args {
+        let ArgClause::Named(arg_clause) = arg.arg_clause(db.upcast()) else {
+            return Err(error_at(db, arg, "Unexpected argument"));
+        };
+        let key = arg_clause.name(db.upcast()).text(db.upcast());
+        let value = arg_clause.value(db.upcast());
+        match key.as_str() {
+            "kind" => {
+                as_byte_array = match get_identifier_option(db, &key, &value)?.as_str() {
+                    "array" => false,
+                    "bytearray" => true,
+                    name => {
+                        return Err(error_at(
+                            db,
+                            &value,
+                            format!(
+                                "Unsupported kind: {}, expected one of array, bytearray",
+                                name
+                            ),
+                        ))
+                    }
+                }
+            }
+            _ => {
+                return Err(error_at(
+                    db,
+                    &arg_clause,
+                    format!("Unexpected argument: {}", key),
+                ))
+            }
+        }
+    }
+    Ok(as_byte_array)
+}
+
+/// Expands the socket address environment variable macro.
+/// Returns the octets and the port as a tuple expression or a diagnostic error if the variable is not set or is not a valid address.
+pub(crate) fn expand_env_socketaddr_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_socketaddr!", token_stream, &db)?;
+    if macro_args.len() > 1 {
+        return Err(error_at(
+            &db,
+            &macro_args[1],
+            "env_socketaddr! accepts only the environment variable name",
+        ));
+    }
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+    let val = lookup_env_var(env, &env_var_name)?
+        .ok_or_else(|| not_set_error(&db, &macro_args[0], &env_var_name, None))?;
+    let (octets, port) = parse_socketaddr_value(&env_var_name, &val)?;
+
+    Ok(TokenStream::new(format!(
+        "({}, {}, {}, {}, {})",
+        octets[0], octets[1], octets[2], octets[3], port
+    )))
+}
+
+/// Expands the batch environment variable macro.
+/// Returns the values of the environment variables as a tuple expression or a diagnostic error if any of them is not set or is not numeric.
+pub(crate) fn expand_env_all_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_all!", token_stream, &db)?;
+
+    let mut values = Vec::with_capacity(macro_args.len());
+    let mut missing = Vec::new();
+    for arg in &macro_args {
+        let (env_var_name, default) = get_batch_item(&db, arg)?;
+        let numeric_val = match lookup_env_var(env, &env_var_name)? {
+            Some(val) => parse_numeric_value(&env_var_name, val.trim())?
+                .ok_or_else(|| numeric_parse_error(&env_var_name, &val))?,
+            None => match default {
+                Some(default) => default,
+                None => {
+                    missing.push((arg, env_var_name));
+                    continue;
+                }
+            },
+        };
+        values.push(
+            check_felt_range(&env_var_name, EnvValue::Numeric(numeric_val), false)?.to_literal(),
+        );
+    }
+
+    match missing.as_slice() {
+        [] => {}
+        [(arg, env_var_name)] => return Err(not_set_error(&db, arg, env_var_name, None)),
+        _ => {
+            let names: Vec<&str> = missing.iter().map(|(_, name)| name.as_str()).collect();
+            return Err(Diagnostic::error(format!(
+                "Environment variables {} not set",
+                names.join(", ")
+            )));
+        }
+    }
+
+    // A tuple with a single element needs a trailing comma.
+    if let [value] = values.as_slice() {
+        return Ok(TokenStream::new(format!("({},)", value)));
+    }
+    Ok(TokenStream::new(format!("({})", values.join(", "))))
+}
+
+/// Parses an argument of the batch macro, which is a variable name or a `(name, default)` pair.
+/// Returns the name and the numeric default, or a diagnostic error if the argument has another form.
+fn get_batch_item(
+    db: &SimpleParserDatabase,
+    arg: &Arg,
+) -> Result<(String, Option<BigInt>), Diagnostic> {
+    let expected = "Expected environment variable name or (name, default) pair";
+    let ArgClause::Unnamed(arg_clause) = arg.arg_clause(db.upcast()) else {
+        return Err(error_at(db, arg, expected));
+    };
+    match arg_clause.value(db.upcast()) {
+        name_expr @ Expr::String(_) => Ok((get_string_option(db, "name", &name_expr)?, None)),
+        Expr::Tuple(tuple) => {
+            let elements = tuple.expressions(db.upcast()).elements(db.upcast());
+            let [name_expr, default_expr] = elements.as_slice() else {
+                return Err(error_at(db, arg, expected));
+            };
+            Ok((
+                get_string_option(db, "name", name_expr)?,
+                Some(get_numeric_literal(db, default_expr)?),
+            ))
+        }
+        _ => Err(error_at(db, arg, expected)),
+    }
+}
+
+/// Expands the UUID environment variable macro.
+/// Returns the packed UUID as a numeric literal or a diagnostic error if the variable is not set or is not a canonical UUID.
+pub(crate) fn expand_env_uuid_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_uuid!", token_stream, &db)?;
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+    let mut endian = Endian::Big;
+    for arg in &macro_args[1..] {
+        endian = get_endian_option(&db, arg)?;
+    }
+    let val = lookup_env_var(env, &env_var_name)?
+        .ok_or_else(|| not_set_error(&db, &macro_args[0], &env_var_name, None))?;
+    let uuid = parse_uuid_value(&env_var_name, &val)?;
+
+    Ok(TokenStream::new(endian.pack(&uuid).to_string()))
+}
+
+/// Expands the color environment variable macro.
+/// Returns the packed color as a numeric literal or a diagnostic error if the variable is not set or is not a valid color.
+pub(crate) fn expand_env_color_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_color!", token_stream, &db)?;
+    if let Some(extra_arg) = macro_args.get(2) {
+        return Err(error_at(&db, extra_arg, "Unexpected argument"));
+    }
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+
+    let color = match lookup_env_var(env, &env_var_name)? {
+        Some(val) => parse_color_value(&env_var_name, &val)?,
+        None => {
+            if macro_args.len() == 2 {
+                let ArgClause::Unnamed(default_arg) = macro_args[1].arg_clause(db.upcast()) else {
+                    return Err(error_at(
+                        &db,
+                        &macro_args[1],
+                        "Expected unnamed default argument",
+                    ));
+                };
+                let default_expr = default_arg.value(db.upcast());
+                let default = get_string_option(&db, "default", &default_expr)?;
+                parse_color_value(&env_var_name, &default)
+                    .map_err(|err| error_at(&db, &default_expr, err.message))?
+            } else {
+                return Err(not_set_error(
+                    &db,
+                    &macro_args[0],
+                    &env_var_name,
+                    Some(("env_color!", "\"#000000\"")),
+                ));
+            }
+        }
+    };
+
+    Ok(TokenStream::new(color.to_string()))
+}
+
+/// Expands the semantic version environment variable macro.
+/// Returns the version components as a tuple expression or a diagnostic error if the variable is not set or is not a valid version.
+pub(crate) fn expand_env_semver_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_semver!", token_stream, &db)?;
+    if macro_args.len() > 1 {
+        return Err(error_at(
+            &db,
+            &macro_args[1],
+            "env_semver! accepts only the environment variable name",
+        ));
+    }
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+    let val = lookup_env_var(env, &env_var_name)?
+        .ok_or_else(|| not_set_error(&db, &macro_args[0], &env_var_name, None))?;
+    let [major, minor, patch] = parse_semver_value(&env_var_name, &val)?;
+
+    Ok(TokenStream::new(format!(
+        "({}, {}, {})",
+        major, minor, patch
+    )))
+}
+
+/// Expands the environment variable assertion macro.
+/// Returns the unit value or a diagnostic error if the variable is not set or doesn't equal the expected value.
+pub(crate) fn expand_env_assert_eq_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_assert_eq!", token_stream, &db)?;
+    let [name_arg, expected_arg] = macro_args.as_slice() else {
+        return Err(Diagnostic::error(
+            "env_assert_eq! expects the environment variable name and the expected value",
+        ));
+    };
+
+    let env_var_name = get_env_variable_name(db.upcast(), &name_arg.arg_clause(db.upcast()))?;
+    let ArgClause::Unnamed(expected_arg) = expected_arg.arg_clause(db.upcast()) else {
+        return Err(error_at(
+            &db,
+            expected_arg,
+            "Expected unnamed expected value argument",
+        ));
+    };
+    let expected = get_string_option(&db, "env_assert_eq!", &expected_arg.value(db.upcast()))?;
+
+    let val = lookup_env_var(env, &env_var_name)?
+        .ok_or_else(|| not_set_error(&db, name_arg, &env_var_name, None))?;
+    if val != expected {
+        return Err(Diagnostic::error(format!(
+            "Environment variable {} is {:?}, expected {:?}",
+            env_var_name,
+            truncate_value(&val),
+            expected
+        )));
+    }
+
+    Ok(TokenStream::new("()".to_string()))
+}
+
+/// Expands the JSON environment variable macro.
+/// Returns the selected integer as a numeric literal or a diagnostic error if the variable is not set,
+/// is not valid JSON or the path doesn't lead to an integer.
+pub(crate) fn expand_env_json_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_json!", token_stream, &db)?;
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+    let path = get_json_path_option(&db, &macro_args[1..])?;
+    let val = lookup_env_var(env, &env_var_name)?
+        .ok_or_else(|| not_set_error(&db, &macro_args[0], &env_var_name, None))?;
+    let numeric_val = parse_json_value(&env_var_name, &val, &path)?;
+
+    let env_value = check_felt_range(&env_var_name, EnvValue::Numeric(numeric_val), false)?;
+    Ok(TokenStream::new(env_value.to_literal()))
+}
+
+/// Parses the arguments following the environment variable name of the JSON macro, which must be `path:`.
+/// Returns the path or a diagnostic error if it is missing or an unexpected argument is given.
+fn get_json_path_option(db: &SimpleParserDatabase, args: &[Arg]) -> Result<String, Diagnostic> {
+    let mut path = None;
+    for arg in args {
+        let ArgClause::Named(arg_clause) = arg.arg_clause(db.upcast()) else {
+            return Err(error_at(db, arg, "Unexpected argument"));
+        };
+        let key = arg_clause.name(db.upcast()).text(db.upcast());
+        let value = arg_clause.value(db.upcast());
+        match key.as_str() {
+            "path" => path = Some(get_string_option(db, &key, &value)?),
+            _ => {
+                return Err(error_at(
+                    db,
+                    &arg_clause,
+                    format!("Unexpected argument: {}", key),
+                ))
+            }
+        }
+    }
+    match path {
+        Some(path) if !path.is_empty() => Ok(path),
+        _ => Err(Diagnostic::error(
+            "env_json! requires a non-empty path: argument",
+        )),
+    }
+}
+
+/// Parses the value of an environment variable as JSON and selects an integer by a dot-separated path.
+/// Returns the integer or a diagnostic error if the JSON is malformed, a field is missing or the selected value is not an integer.
+fn parse_json_value(env_var_name: &str, val: &str, path: &str) -> Result<BigInt, Diagnostic> {
+    let json: serde_json::Value = serde_json::from_str(val).map_err(|err| {
+        Diagnostic::error(format!(
+            "Failed to parse JSON of environment variable {}: {}",
+            env_var_name, err
+        ))
+    })?;
+
+    let mut selected = &json;
+    for key in path.split('.') {
+        let field = match selected {
+            serde_json::Value::Object(object) => object.get(key),
+            serde_json::Value::Array(array) => {
+                key.parse().ok().and_then(|index: usize| array.get(index))
+            }
+            _ => None,
+        };
+        selected = field.ok_or_else(|| {
+            Diagnostic::error(format!(
+                "Field {} not found in JSON of environment variable {}",
+                path, env_var_name
+            ))
+        })?;
+    }
+
+    let serde_json::Value::Number(number) = selected else {
+        return Err(json_integer_error(env_var_name, path, selected));
+    };
+    // Numbers keep their original text, so integers beyond 64 bits are not rounded.
+    let text = number.to_string();
+    let digits = text.strip_prefix('-').unwrap_or(&text);
+    if digits.is_empty() || !digits.bytes().all(|byte| byte.is_ascii_digit()) {
+        return Err(json_integer_error(env_var_name, path, selected));
+    }
+    text.parse()
+        .map_err(|_| json_integer_error(env_var_name, path, selected))
+}
+
+/// Builds the diagnostic error for a JSON field that is not an integer.
+fn json_integer_error(env_var_name: &str, path: &str, value: &serde_json::Value) -> Diagnostic {
+    Diagnostic::error(format!(
+        "Field {} in JSON of environment variable {} is not an integer: {}",
+        path,
+        env_var_name,
+        truncate_value(&value.to_string())
+    ))
+}
+
+/// Default number of decimal places of `env_f64_fixed!`, matching 18-decimal tokens.
+const DEFAULT_FIXED_SCALE: u32 = 18;
+
+/// Largest scale accepted by `env_f64_fixed!`, as `10^76` already exceeds the `felt252` range.
+const MAX_FIXED_SCALE: u32 = 76;
+
+/// Expands the fixed-point environment variable macro.
+/// Returns the scaled decimal as a numeric literal or a diagnostic error if the variable is not set,
+/// is not a decimal number or has more decimal places than the scale.
+pub(crate) fn expand_env_f64_fixed_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_f64_fixed!", token_stream, &db)?;
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+    let scale = get_scale_option(&db, &macro_args[1..])?;
+    let val = lookup_env_var(env, &env_var_name)?
+        .ok_or_else(|| not_set_error(&db, &macro_args[0], &env_var_name, None))?;
+    let fixed = parse_fixed_value(&env_var_name, &val, scale)?;
+
+    let env_value = check_felt_range(&env_var_name, EnvValue::Numeric(fixed), false)?;
+    Ok(TokenStream::new(env_value.to_literal()))
+}
+
+/// Parses the arguments following the environment variable name of the fixed-point macro, which can only be `scale:`.
+/// Returns the scale, 18 by default, or a diagnostic error if it is out of range or an unexpected argument is given.
+fn get_scale_option(db: &SimpleParserDatabase, args: &[Arg]) -> Result<u32, Diagnostic> {
+    let mut scale = DEFAULT_FIXED_SCALE;
+    for arg in args {
+        let ArgClause::Named(arg_clause) = arg.arg_clause(db.upcast()) else {
+            return Err(error_at(db, arg, "Unexpected argument"));
+        };
+        let key = arg_clause.name(db.upcast()).text(db.upcast());
+        let value = arg_clause.value(db.upcast());
+        match key.as_str() {
+            "scale" => {
+                scale = get_numeric_option(db, &key, &value)?
+                    .try_into()
+                    .ok()
+                    .filter(|scale| *scale <= MAX_FIXED_SCALE)
+                    .ok_or_else(|| {
+                        error_at(
+                            db,
+                            &value,
+                            format!("scale must be between 0 and {}", MAX_FIXED_SCALE),
+                        )
+                    })?
+            }
+            _ => {
+                return Err(error_at(
+                    db,
+                    &arg_clause,
+                    format!("Unexpected argument: {}", key),
+                ))
+            }
+        }
+    }
+    Ok(scale)
+}
+
+/// Expands the constants attribute macro given all the variables of the environment.
+/// Returns the module with the constant declarations, sorted by name, appended to its body,
+/// along with warnings for the skipped variables, or a diagnostic error if the attribute is not applied to a module with a body,
+/// the prefix is not a string, the `.env` file can't be parsed or a value doesn't fit into `felt252`.
+pub(crate) fn expand_env_consts_macro(
+    attr: impl ToString,
+    item: impl ToString,
+    env: &impl Env,
+) -> Result<(TokenStream, Vec<Diagnostic>), Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let item = item.to_string();
+    let module_error = || {
+        Diagnostic::error(
+            "#[env_consts] can only be applied to a module with a body, like mod config {}",
+        )
+    };
+    let ModuleItem::Module(module) = parse_module_item("#[env_consts]", &item, &db)? else {
+        return Err(module_error());
+    };
+    let MaybeModuleBody::Some(body) = module.body(db.upcast()) else {
+        return Err(module_error());
+    };
+
+    let macro_args = parse_attribute_args("env_consts!", attr, &db)?;
+    let Some(prefix_arg) = macro_args.first() else {
+        return Err(Diagnostic::error(
+            "Please specify the variable name prefix, like #[env_consts(\"MYAPP_\")]",
+        ));
+    };
+    if macro_args.len() > 1 {
+        return Err(error_at(
+            &db,
+            &macro_args[1],
+            "#[env_consts] accepts only the variable name prefix",
+        ));
+    }
+    let ArgClause::Unnamed(prefix_arg) = prefix_arg.arg_clause(db.upcast()) else {
+        return Err(error_at(&db, prefix_arg, "Expected variable name prefix"));
+    };
+    let prefix_expr = prefix_arg.value(db.upcast());
+    let prefix = get_string_option(&db, "#[env_consts]", &prefix_expr)?;
+    if prefix.is_empty() {
+        return Err(error_at(
+            &db,
+            &prefix_expr,
+            "Variable name prefix must not be empty",
+        ));
+    }
+
+    // Variables are visited by name, so that the same constant is always skipped as a duplicate.
+    let mut env_vars = match env.dotenv() {
+        Ok(dotenv_vars) => dotenv_vars.clone().into_iter().collect::<BTreeMap<_, _>>(),
+        Err(err) => {
+            return Err(Diagnostic::error(format!(
+                "Failed to parse .env file {}",
+                err
+            )))
+        }
+    };
+    env_vars.extend(env.vars());
+
+    let mut warnings = Vec::new();
+    let mut consts = Vec::new();
+    let mut declared = HashMap::new();
+    for (env_var_name, val) in env_vars {
+        let Some(const_name) = env_var_name.strip_prefix(&prefix) else {
+            continue;
+        };
+        let const_name = const_name.to_uppercase();
+        if !is_identifier(&const_name) {
+            warnings.push(Diagnostic::warn(format!(
+                "Skipping environment variable {}: {} is not a valid constant name",
+                env_var_name, const_name
+            )));
+            continue;
+        }
+        if let Some(declared_by) = declared.get(&const_name) {
+            warnings.push(Diagnostic::warn(format!(
+                "Skipping environment variable {}: {} is already declared from {}",
+                env_var_name, const_name, declared_by
+            )));
+            continue;
+        }
+        // Malformed or out of range values of unrelated variables don't fail the whole module.
+        let env_value = match parse_env_value(&env_var_name, &val)
+            .and_then(|env_value| check_felt_range(&env_var_name, env_value, false))
+        {
+            Ok(EnvValue::Numeric(numeric_val)) => numeric_val,
+            Ok(_) => {
+                warnings.push(Diagnostic::warn(format!(
+                    "Skipping environment variable {}: {} is not numeric, and constants can't hold a ByteArray",
+                    env_var_name,
+                    truncate_value(&val)
+                )));
+                continue;
+            }
+            Err(err) => {
+                warnings.push(Diagnostic::warn(format!(
+                    "Skipping environment variable {}: {}",
+                    env_var_name, err.message
+                )));
+                continue;
+            }
+        };
+        declared.insert(const_name.clone(), env_var_name.clone());
+        consts.push((const_name, env_value));
+    }
+    consts.sort();
+
+    let declarations: String = consts
+        .into_iter()
+        .map(|(const_name, numeric_val)| {
+            format!("    const {}: felt252 = {};\n", const_name, numeric_val)
+        })
+        .collect();
+    // The declarations are appended to the body, before its closing brace.
+    let rbrace_offset = body
+        .rbrace(db.upcast())
+        .as_syntax_node()
+        .span_without_trivia(db.upcast())
+        .start
+        .as_u32() as usize;
+    Ok((
+        TokenStream::new(format!(
+            "{}\n{}{}",
+            &item[..rbrace_offset],
+            declarations,
+            &item[rbrace_offset..]
+        )),
+        warnings,
+    ))
+}
+
+/// Checks whether a name can be used as a Cairo identifier, i.e. a letter or underscore followed by alphanumerics or underscores.
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Expands the flags environment variable macro.
+/// Returns the bitwise OR of the values as a numeric literal or a diagnostic error naming the variable
+/// whose value is not a non-negative number.
+pub(crate) fn expand_env_flags_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_flags!", token_stream, &db)?;
+
+    let (env_var_names, option_args) = get_env_variable_names(&db, &macro_args)?;
+    let mut default = BigInt::ZERO;
+    for arg in option_args {
+        let ArgClause::Named(arg_clause) = arg.arg_clause(db.upcast()) else {
+            return Err(error_at(&db, arg, "Unexpected argument"));
+        };
+        let key = arg_clause.name(db.upcast()).text(db.upcast());
+        let value = arg_clause.value(db.upcast());
+        match key.as_str() {
+            "default" => {
+                default = get_numeric_option(&db, &key, &value)?;
+                if default.sign() == Sign::Minus {
+                    return Err(error_at(&db, &value, "Default flags must not be negative"));
+                }
+            }
+            _ => {
+                return Err(error_at(
+                    &db,
+                    &arg_clause,
+                    format!("Unexpected argument: {}", key),
+                ))
+            }
+        }
+    }
+
+    let mut flags = BigInt::ZERO;
+    for env_var_name in &env_var_names {
+        let flag = match lookup_env_var(env, env_var_name)? {
+            Some(val) => parse_numeric_value(env_var_name, val.trim())?
+                .ok_or_else(|| numeric_parse_error(env_var_name, &val))?,
+            None => default.clone(),
+        };
+        if flag.sign() == Sign::Minus {
+            return Err(Diagnostic::error(format!(
+                "{}={} is negative, flags must not be negative",
+                env_var_name, flag
+            )));
+        }
+        flags |= flag;
+    }
+
+    let env_value = check_felt_range(&env_var_names[0], EnvValue::Numeric(flags), false)?;
+    Ok(TokenStream::new(env_value.to_literal()))
+}
+
+/// Expands the characters environment variable macro.
+/// Returns the characters as an `array!` expression of short strings or a diagnostic error
+/// if the variable is not set or has non-ASCII characters.
+pub(crate) fn expand_env_chars_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_chars!", token_stream, &db)?;
+    if macro_args.len() > 1 {
+        return Err(error_at(
+            &db,
+            &macro_args[1],
+            "env_chars! accepts only the environment variable name",
+        ));
+    }
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+    let val = lookup_env_var(env, &env_var_name)?
+        .ok_or_else(|| not_set_error(&db, &macro_args[0], &env_var_name, None))?;
+    if let Some((position, c)) = val.chars().enumerate().find(|(_, c)| !c.is_ascii()) {
+        return Err(Diagnostic::error(format!(
+            "{}={} has the non-ASCII character `{}` at position {}, only ASCII characters are supported",
+            env_var_name,
+            truncate_value(&val),
+            c,
+            position + 1
+        )));
+    }
+
+    let elements: Vec<String> = val
+        .bytes()
+        .map(|byte| short_string_literal(&[byte]))
+        .collect();
+    Ok(TokenStream::new(format!("array![{}]", elements.join(", "))))
+}
+
+/// Expands the map environment variable macro.
+/// Returns a block expression building the dictionary or a diagnostic error if the variable is not set
+/// or a pair is malformed.
+pub(crate) fn expand_env_map_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_map!", token_stream, &db)?;
+    if macro_args.len() > 1 {
+        return Err(error_at(
+            &db,
+            &macro_args[1],
+            "env_map! accepts only the environment variable name",
+        ));
+    }
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+    let val = lookup_env_var(env, &env_var_name)?
+        .ok_or_else(|| not_set_error(&db, &macro_args[0], &env_var_name, None))?;
+
+    let mut statements =
+        vec!["let mut dict: core::dict::Felt252Dict<felt252> = Default::default();".to_string()];
+    for (key, numeric_val) in parse_map_value(&env_var_name, &val)? {
+        statements.push(format!(
+            "dict.insert({}, {});",
+            short_string_literal(key.as_bytes()),
+            numeric_val
+        ));
+    }
+    statements.push("dict".to_string());
+    Ok(TokenStream::new(format!("{{ {} }}", statements.join(" "))))
+}
+
+/// Parses the value of an environment variable as comma-separated `key:value` pairs, like `a:1,b:2`.
+/// Returns the keys with their numeric values or a diagnostic error naming the malformed pair.
+fn parse_map_value(env_var_name: &str, val: &str) -> Result<Vec<(String, BigInt)>, Diagnostic> {
+    if val.trim().is_empty() {
+        return Ok(vec![]);
+    }
+
+    let mut keys = HashSet::new();
+    let mut pairs = Vec::new();
+    for pair in val.split(',').map(str::trim) {
+        let pair_error = |reason: &str| {
+            Diagnostic::error(format!(
+                "Invalid pair `{}` in environment variable {}: {}",
+                truncate_value(pair),
+                env_var_name,
+                reason
+            ))
+        };
+        let (key, value) = pair
+            .split_once(':')
+            .ok_or_else(|| pair_error("expected key:value"))?;
+        let (key, value) = (key.trim(), value.trim());
+        if key.is_empty() || !key.is_ascii() || key.len() > 31 {
+            return Err(pair_error(
+                "the key must be a short string of 1 to 31 ASCII characters",
+            ));
+        }
+        let numeric_val = parse_numeric_value(env_var_name, value)
+            .ok()
+            .flatten()
+            .ok_or_else(|| pair_error("the value is not a number"))?;
+        check_felt_range(env_var_name, EnvValue::Numeric(numeric_val.clone()), false)?;
+        if !keys.insert(key) {
+            return Err(pair_error("the key is given more than once"));
+        }
+        pairs.push((key.to_string(), numeric_val));
+    }
+    Ok(pairs)
+}
+
+/// Expands the path environment variable macro.
+/// Returns the path as a `ByteArray` literal or a diagnostic error if the variable is not set
+/// or the path doesn't exist and is required to.
+pub(crate) fn expand_env_path_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_path!", token_stream, &db)?;
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+    let mut must_exist = true;
+    for arg in &macro_args[1..] {
+        let ArgClause::Named(arg_clause) = arg.arg_clause(db.upcast()) else {
+            return Err(error_at(&db, arg, "Unexpected argument"));
+        };
+        let key = arg_clause.name(db.upcast()).text(db.upcast());
+        let value = arg_clause.value(db.upcast());
+        match key.as_str() {
+            "must_exist" => must_exist = get_bool_option(&key, &value)?,
+            _ => {
+                return Err(error_at(
+                    &db,
+                    &arg_clause,
+                    format!("Unexpected argument: {}", key),
+                ))
+            }
+        }
+    }
+
+    let val = lookup_env_var(env, &env_var_name)?
+        .ok_or_else(|| not_set_error(&db, &macro_args[0], &env_var_name, None))?;
+    let path = package_path(env, &val);
+    if must_exist && !path.exists() {
+        return Err(Diagnostic::error(format!(
+            "Path {} given in environment variable {} does not exist, pass must_exist: false to allow it",
+            path.display(),
+            env_var_name
+        )));
+    }
+
+    Ok(TokenStream::new(string_literal(
+        path.to_string_lossy().as_bytes(),
+    )))
+}
+
+/// Expands the enum environment variable macro.
+/// Returns the index of the value among the variants as a numeric literal or a diagnostic error
+/// if the variable is not set or is not one of the variants.
+pub(crate) fn expand_env_enum_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_enum!", token_stream, &db)?;
+
+    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
+    let Some(ArgClause::Unnamed(variants_arg)) =
+        macro_args.get(1).map(|arg| arg.arg_clause(db.upcast()))
+    else {
+        return Err(Diagnostic::error(
+            "env_enum! expects the list of variants after the environment variable name",
+        ));
+    };
+    let variants_expr = variants_arg.value(db.upcast());
+    let variants = get_string_array_option(&db, "env_enum!", &variants_expr)?;
+    if variants.is_empty() {
+        return Err(error_at(
+            &db,
+            &variants_expr,
+            "env_enum! expects at least one variant",
+        ));
+    }
+
+    let mut start = BigInt::ZERO;
+    for arg in &macro_args[2..] {
+        let ArgClause::Named(arg_clause) = arg.arg_clause(db.upcast()) else {
+            return Err(error_at(&db, arg, "Unexpected argument"));
+        };
+        let key = arg_clause.name(db.upcast()).text(db.upcast());
+        let value = arg_clause.value(db.upcast());
+        match key.as_str() {
+            "start" => start = get_numeric_option(&db, &key, &value)?,
+            _ => {
+                return Err(error_at(
+                    &db,
+                    &arg_clause,
+                    format!("Unexpected argument: {}", key),
+                ))
+            }
+        }
+    }
+
+    let val = lookup_env_var(env, &env_var_name)?
+        .ok_or_else(|| not_set_error(&db, &macro_args[0], &env_var_name, None))?;
+    let index = variants
+        .iter()
+        .position(|variant| *variant == val)
+        .ok_or_else(|| {
+            Diagnostic::error(format!(
+                "{}={} is not one of the variants: {}",
+                env_var_name,
+                truncate_value(&val),
+                variants.join(", ")
+            ))
+        })?;
+
+    let env_value = check_felt_range(&env_var_name, EnvValue::Numeric(start + index), false)?;
+    Ok(TokenStream::new(env_value.to_literal()))
+}
+
+/// Expands the macro naming the first set environment variable.
+/// Returns the name as a `ByteArray` literal or a diagnostic error if none of the variables is set and there is no default.
+pub(crate) fn expand_env_which_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args("env_which!", token_stream, &db)?;
+    if let Some(extra_arg) = macro_args.get(2) {
+        return Err(error_at(
+            &db,
+            extra_arg,
+            "env_which! accepts only a list of names and a default",
+        ));
+    }
+
+    let ArgClause::Unnamed(names_arg) = macro_args[0].arg_clause(db.upcast()) else {
+        return Err(error_at(
+            &db,
+            &macro_args[0],
+            "env_which! expects a list of environment variable names",
+        ));
+    };
+    let names_expr = names_arg.value(db.upcast());
+    let names = get_string_array_option(&db, "env_which!", &names_expr)?;
+    if names.is_empty() {
+        return Err(error_at(
+            &db,
+            &names_expr,
+            "env_which! expects at least one name",
+        ));
+    }
+    let default = match macro_args.get(1).map(|arg| arg.arg_clause(db.upcast())) {
+        Some(ArgClause::Unnamed(default_arg)) => Some(get_string_option(
+            &db,
+            "the default name",
+            &default_arg.value(db.upcast()),
+        )?),
+        Some(_) => {
+            return Err(error_at(
+                &db,
+                &macro_args[1],
+                "Expected unnamed default name",
+            ))
+        }
+        None => None,
+    };
+
+    let mut set_name = None;
+    for name in &names {
+        if lookup_env_var(env, name)?.is_some() {
+            set_name = Some(name);
+            break;
+        }
+    }
+    let name = set_name.or(default.as_ref()).ok_or_else(|| {
+        Diagnostic::error(format!(
+            "None of the environment variables {} is set",
+            names.join(", ")
+        ))
+    })?;
+    Ok(TokenStream::new(string_literal(name.as_bytes())))
+}
+
+/// Variable holding the timestamp to use instead of the current time, for reproducible builds.
+const SOURCE_DATE_EPOCH_VAR: &str = "SOURCE_DATE_EPOCH";
+
+/// Expands the build timestamp macro.
+/// Returns the timestamp as a numeric literal or a diagnostic error if arguments are given
+/// or `SOURCE_DATE_EPOCH` is not a valid timestamp.
+pub(crate) fn expand_env_timestamp_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<TokenStream, Diagnostic> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_arg_list("env_timestamp!", token_stream, &db)?;
+    if let Some(arg) = macro_args.first() {
+        return Err(error_at(&db, arg, "env_timestamp! takes no arguments"));
+    }
+
+    let timestamp = match lookup_env_var(env, SOURCE_DATE_EPOCH_VAR)? {
+        Some(val) => val.trim().parse::<u64>().map_err(|_| {
+            Diagnostic::error(format!(
+                "{}={} is not a valid Unix timestamp",
+                SOURCE_DATE_EPOCH_VAR,
+                truncate_value(&val)
+            ))
+        })?,
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|err| Diagnostic::error(format!("Failed to read the current time: {}", err)))?
+            .as_secs(),
+    };
+    Ok(TokenStream::new(timestamp.to_string()))
+}
+
+/// Parses the arguments following the environment variable name of the list macros, which can only be `sep:`.
+/// Returns the separator, a comma by default, or a diagnostic error if an unexpected argument is given.
+fn get_separator_option(db: &SimpleParserDatabase, args: &[Arg]) -> Result<String, Diagnostic> {
+    let mut separator = ",".to_string();
+    for arg in args {
+        let ArgClause::Named(arg_clause) = arg.arg_clause(db.upcast()) else {
+            return Err(error_at(db, arg, "Unexpected argument"));
+        };
+        let key = arg_clause.name(db.upcast()).text(db.upcast());
+        let value = arg_clause.value(db.upcast());
+        match key.as_str() {
+            "sep" => separator = get_string_option(db, &key, &value)?,
+            _ => {
+                return Err(error_at(
+                    db,
+                    &arg_clause,
+                    format!("Unexpected argument: {}", key),
+                ))
+            }
+        }
+    }
+    if separator.is_empty() {
+        return Err(Diagnostic::error("sep must not be empty"));
+    }
+    Ok(separator)
+}
+
+/// Splits a list value on the separator and parses each trimmed element as a number.
+/// Returns the elements as numeric literals or a diagnostic error naming the element that failed to parse
+/// or doesn't fit into `felt252`.
+fn parse_numeric_list(
+    env_var_name: &str,
+    val: &str,
+    separator: &str,
+) -> Result<Vec<String>, Diagnostic> {
+    if val.trim().is_empty() {
+        return Ok(vec![]);
+    }
+
+    val.split(separator)
+        .map(str::trim)
+        .map(|element| match parse_numeric_value(env_var_name, element) {
+            Ok(Some(numeric_val)) => {
+                Ok(
+                    check_felt_range(env_var_name, EnvValue::Numeric(numeric_val), false)?
+                        .to_literal(),
+                )
+            }
+            _ => Err(Diagnostic::error(format!(
+                "Failed to parse element `{}` of environment variable {} as a number",
+                element, env_var_name
+            ))),
+        })
+        .collect()
+}
@@ -2,147 +2,2798 @@
 // Based on the code from Alexandria library (https://github.com/keep-starknet-strange/alexandria)
 // Copyright (c) 2025 Alexandria Contributors
 
-use std::str::FromStr;
+use std::collections::HashMap;
+use std::env::VarError;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, PoisonError};
 
-use cairo_lang_filesystem::ids::{FileKind, FileLongId, VirtualFile};
-use cairo_lang_macro::{inline_macro, Diagnostic, ProcMacroResult, TokenStream};
-use cairo_lang_parser::db::ParserGroup;
-use cairo_lang_parser::utils::SimpleParserDatabase;
-use cairo_lang_syntax::node::ast::{ArgClause, Expr, ExprInlineMacro, WrappedArgList};
-use cairo_lang_utils::{Intern, Upcast};
-use num_bigint::BigInt;
+use cairo_lang_macro::{
+    attribute_macro, inline_macro, Diagnostic, Diagnostics, ProcMacroResult, TokenStream,
+};
 
-/// Returns the value of an environment variable as a numeric value.
+use crate::dotenv::DotenvVars;
+use crate::expand::{
+    expand_env_all_macro, expand_env_array_macro, expand_env_assert_eq_macro,
+    expand_env_bool_macro, expand_env_bps_macro, expand_env_bytes_macro, expand_env_chars_macro,
+    expand_env_color_macro, expand_env_consts_macro, expand_env_duration_macro,
+    expand_env_enum_macro, expand_env_f64_fixed_macro, expand_env_flags_macro,
+    expand_env_json_macro, expand_env_map_macro, expand_env_path_macro, expand_env_semver_macro,
+    expand_env_shortstring_macro, expand_env_socketaddr_macro, expand_env_span_macro,
+    expand_env_str_macro, expand_env_timestamp_macro, expand_env_u256_macro, expand_env_uuid_macro,
+    expand_env_which_macro, expand_option_env_macro,
+};
+use crate::resolve::{expand_env_macro, expand_env_with_flag_macro};
+use crate::syntax::non_unicode_message;
+
+mod dotenv;
+mod expand;
+mod manifest;
+mod options;
+mod resolve;
+mod syntax;
+mod value;
+
+/// Returns the value of an environment variable as a numeric or a string value.
 ///
+/// If the value parses as a number, the macro expands to a numeric literal,
+/// otherwise it expands to a `ByteArray` string literal.
+/// Surrounding whitespace is ignored when parsing numbers, while strings are preserved as is
+/// unless `trim: true` is given.
+/// With `strip_quotes: true`, a matching pair of single or double quotes surrounding the value is removed.
+/// Negative numbers are expanded as is, e.g. `-5`: the expansion is parsed as a standalone
+/// expression, so it does not need parentheses when used inside a larger expression.
+/// For the same reason, the expansion is valid in a `const` initializer as well as in a `let` or any other expression.
+///
+/// Variables that are not set in the process environment are looked up in the closest `.env` file,
+/// found by walking up from the current directory.
 /// If the environment variable is not set, the macro will return a diagnostic error.
 /// You can also specify a default value that will be returned if the environment variable is not set.
+/// A variable set to a value that is not valid UTF-8 is reported as such instead of being treated as not set.
+///
+/// Numeric values can be restricted to an inclusive range with `min:` and `max:`,
+/// and the length of string values in bytes with `min_len:` and `max_len:`.
+/// String values can be restricted to a set of allowed values with `one_of:`, or to a regular expression
+/// with `matches:`, expanding to a `ByteArray`.
+/// Numeric values must fit into `felt252`, unless `wrapping: true` is given to reduce them modulo the field prime.
+/// A different field can be targeted by giving its prime as `prime:`.
+/// Numeric-looking values longer than 100 characters are rejected before parsing, a different limit can be given as `max_digits:`.
+///
+/// Several variable names can be given, in which case the first one that is set wins,
+/// or all of them are joined into a string with the separator given as `join:`.
+/// With `strip_prefix:`, a name that is not set is retried with the prefix removed.
+/// Empty or whitespace-only values are treated as not set, unless `allow_empty: true` is given.
+/// Values listed in `null_values:`, like `["null", "none"]`, are treated as not set too, ignoring case.
+/// Variables that are not set in the process environment are read from the closest `.env` file,
+/// or from the file given as `dotenv:`, relative to the package directory.
+/// With `prefer: dotenv`, the `.env` file takes precedence over the process environment instead.
+/// If `CAIRO_ENV_PREFIX` is set in the process environment, each name is first looked up with that prefix,
+/// then falls back to the bare name.
+/// With `toml: true`, variables that are not set in either are read from the `[tool.env]` table of `Scarb.toml`.
+///
+/// With `suffix:`, a numeric value must fit into the given integer type and is expanded as a typed literal, like `42_u64`.
+/// The automatic detection can be overridden with `kind:` (Cairo reserves `as`),
+/// which accepts `felt`, `bytearray`, `bool` or `hex` for a numeric value expanded as a hexadecimal literal.
+/// With `kind: hex`, negative values are expanded as their two's complement at the bit width given as `width:`.
+/// With `kind: felt`, boolean-like values such as `true` or `off` are expanded to `1` or `0`.
+/// Defaults are coerced to the kind as well: a numeric default becomes its decimal string with `kind: bytearray`,
+/// or `false` and `true` for 0 and 1 with `kind: bool`, and a string default is parsed like a variable value
+/// with the other kinds. With `strict_default: true`, a default that doesn't match the kind is an error instead.
+/// If `CAIRO_ENV_STRING_ONLY` is true in the process environment, values are never detected as numbers
+/// and expand to `ByteArray` literals unless `kind:` is given.
+/// Since all leading string arguments are variable names, a string default must be passed as `default:`.
+/// With `debug: true`, the expansion and the source of the value are logged to stderr.
 ///
 /// For example:
 /// ```
 /// let version: ByteArray = env!("VERSION");
-/// let version: ByteArray = env!("VERSION", 1);
+/// let version: usize = env!("VERSION", 1);
+/// let offset: i32 = env!("OFFSET", -5);
+/// let version: ByteArray = env!("VERSION", default: "dev");
+/// let port: u16 = env!("PORT", default: 8080);
+/// let port: u16 = env!("APP_PORT", "PORT", default: 3000);
+/// let port: u16 = env!("MYAPP_PORT", strip_prefix: "MYAPP_");
+/// let sha: ByteArray = env!("GITHUB_SHA", kind: bytearray);
+/// let build: ByteArray = env!("BUILD", 0, kind: bytearray);  // "0" if BUILD is not set
+/// let salt: felt252 = env!("SALT", kind: hex);
+/// let mask: u8 = env!("MASK", kind: hex, width: 8);
+/// let chain: ByteArray = env!("CHAIN", transform: lowercase);
+/// let url: ByteArray = env!("RPC_URL", dotenv: "config/prod.env");
+/// let url: ByteArray = env!("RPC_URL", prefer: dotenv);
+/// let chain_id: felt252 = env!("CHAIN_ID", toml: true);
+/// let id: ByteArray = env!("NAME", "VERSION", join: "-");
+/// let api_key: ByteArray = env!("API_KEY_FILE", from_file: true);
+/// let rpc_url: ByteArray = env!("PRIMARY_RPC", fallback_var: "SECONDARY_RPC");
+/// let timeout: u64 = env!("TIMEOUT", DEFAULT_TIMEOUT);
+/// let slot: u64 = env!("SLOT", 60 * 60);
+/// let label: ByteArray = env!("LABEL", trim: true);
+/// let name: ByteArray = env!("NAME", strip_quotes: true);
+/// let url: ByteArray = env!("URL", interpolate: true);
+/// let suffix: ByteArray = env!("SUFFIX", allow_empty: true);
+/// let port: u16 = env!("PORT", 8080, null_values: ["null", "none"]);
+/// let api_key: ByteArray = env!("API_KEY", message: "Set API_KEY in your deployment secrets");
+/// let port: u16 = env!("PORT", 8080, warn_on_default: true);
+/// let port: u16 = env!("PORT", 8080, debug: true);
+/// let offset: i64 = env!("OFFSET", signed: i64);
+/// let port: u16 = env!("PORT", unsigned: u16);
+/// let gas = env!("GAS", suffix: u64);
+/// let workers: u8 = env!("WORKERS", min: 1, max: 64);
+/// let label: ByteArray = env!("LABEL", max_len: 31);
+/// let mask: u256 = env!("MASK", max_digits: 258);
+/// let decimals: u8 = env!("DECIMALS", nonzero: true);
+/// let stage: ByteArray = env!("ENV_STAGE", one_of: ["dev", "staging", "prod"]);
+/// let stage: ByteArray = env!("ENV_STAGE", one_of: ["dev", "prod"], case_insensitive: true);
+/// let slug: ByteArray = env!("SLUG", matches: "^[a-z0-9-]+$");
 /// ```
 #[inline_macro]
 pub fn env(token_stream: TokenStream) -> ProcMacroResult {
-    match expand_env_macro(token_stream) {
+    into_proc_macro_result_with_warnings(expand_env_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the value of an environment variable along with whether it was set, as a `(value, is_set)` tuple.
+///
+/// Accepts the same arguments as `env!`, and `is_set` is `false` if the default was used,
+/// so the use of a default can be detected at runtime.
+///
+/// For example:
+/// ```
+/// let (port, is_set): (u16, bool) = env_with_flag!("PORT", 8080);
+/// ```
+#[inline_macro]
+pub fn env_with_flag(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result_with_warnings(expand_env_with_flag_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the value of an environment variable as a `ByteArray` string literal.
+///
+/// Unlike `env!`, the value is never interpreted as a number, so `"123"` stays a string.
+/// You can also specify a string default value that will be returned if the environment variable is not set.
+///
+/// For example:
+/// ```
+/// let tag: ByteArray = env_str!("TAG");
+/// let tag: ByteArray = env_str!("TAG", "dev");
+/// ```
+#[inline_macro]
+pub fn env_str(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_str_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the value of an environment variable wrapped in an `Option`.
+///
+/// Expands to `Option::Some(value)` if the environment variable is set and to `Option::None` otherwise,
+/// so a missing variable never produces a diagnostic error.
+/// The value is interpreted the same way as in `env!`.
+///
+/// For example:
+/// ```
+/// let maybe: Option<felt252> = option_env!("FEATURE_LEVEL");
+/// ```
+#[inline_macro]
+pub fn option_env(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_option_env_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the value of an environment variable as a `bool` literal.
+///
+/// The accepted values are `true`/`false`, `1`/`0`, `yes`/`no` and `on`/`off` (case-insensitive).
+/// You can also specify a `bool` default value that will be returned if the environment variable is not set.
+///
+/// For example:
+/// ```
+/// let enable_logs: bool = env_bool!("ENABLE_LOGS");
+/// let enable_logs: bool = env_bool!("ENABLE_LOGS", false);
+/// ```
+#[inline_macro]
+pub fn env_bool(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_bool_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the value of an environment variable as a `u256` struct expression.
+///
+/// The value can exceed the `felt252` range and is split into the low and high 128-bit limbs,
+/// values that do not fit into 256 bits produce a diagnostic error.
+/// You can also specify a numeric default value that will be returned if the environment variable is not set.
+///
+/// For example:
+/// ```
+/// let initial_supply: u256 = env_u256!("INITIAL_SUPPLY");
+/// let initial_supply: u256 = env_u256!("INITIAL_SUPPLY", 1000);
+/// ```
+#[inline_macro]
+pub fn env_u256(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_u256_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the value of an environment variable as a short string, i.e. a `felt252` encoding its ASCII bytes.
+///
+/// The bytes are packed big-endian, the same way as in a `'abc'` short string literal,
+/// so the value must be at most 31 ASCII characters long.
+/// Pass `endian: little` to pack the first character into the lowest byte instead.
+/// With `utf8: true`, non-ASCII values are accepted and their raw UTF-8 bytes are packed, still at most 31 of them,
+/// so consumers must decode the bytes as UTF-8 rather than as ASCII characters.
+/// You can also specify a short string default value that will be returned if the environment variable is not set.
+///
+/// For example:
+/// ```
+/// let symbol: felt252 = env_shortstring!("SYMBOL");
+/// let symbol: felt252 = env_shortstring!("SYMBOL", 'STRK');
+/// let symbol: felt252 = env_shortstring!("SYMBOL", 'STRK', endian: little);
+/// let symbol: felt252 = env_shortstring!("SYMBOL", utf8: true);
+/// ```
+#[inline_macro]
+pub fn env_shortstring(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_shortstring_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the value of an environment variable as an array of numbers.
+///
+/// The value is split on commas, or on the separator given as `sep:`, and each element is parsed as a number.
+/// An empty value expands to an empty array.
+///
+/// For example:
+/// ```
+/// let allowed_ids: Array<felt252> = env_array!("ALLOWED_IDS");
+/// let allowed_ids: Array<felt252> = env_array!("ALLOWED_IDS", sep: ";");
+/// ```
+#[inline_macro]
+pub fn env_array(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_array_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the value of an environment variable holding a duration as a number of seconds.
+///
+/// The value is a number followed by one of the units `s`, `m`, `h` or `d`, a bare number is in seconds.
+/// You can also specify a numeric default value in seconds that will be returned if the environment variable is not set.
+///
+/// For example:
+/// ```
+/// let session_ttl: u64 = env_duration!("SESSION_TTL");
+/// let session_ttl: u64 = env_duration!("SESSION_TTL", 1800);
+/// ```
+#[inline_macro]
+pub fn env_duration(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_duration_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the value of an environment variable holding a percentage in basis points.
+///
+/// The value is a percentage with at most two decimal places and an optional `%` sign, like `2.5%`,
+/// which is expanded to the percentage multiplied by 100, like `250`. Percentages over 100% produce a diagnostic error.
+/// You can also specify a numeric default value in basis points, at most 10000, that will be returned if the environment variable is not set.
+///
+/// For example:
+/// ```
+/// let fee_bps: u16 = env_bps!("FEE");
+/// let fee_bps: u16 = env_bps!("FEE", 30);
+/// ```
+#[inline_macro]
+pub fn env_bps(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_bps_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the base64-decoded value of an environment variable as an array of bytes.
+///
+/// The bytes are expanded to an `array!` of `u8` literals, or to a `ByteArray` literal with `kind: bytearray`
+/// (Cairo reserves `as`). Invalid base64 produces a diagnostic error.
+///
+/// For example:
+/// ```
+/// let pubkey: Array<u8> = env_bytes!("PUBKEY_B64");
+/// let pubkey: ByteArray = env_bytes!("PUBKEY_B64", kind: bytearray);
+/// ```
+#[inline_macro]
+pub fn env_bytes(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_bytes_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the value of an environment variable holding an IPv4 socket address as a tuple.
+///
+/// The value is an address like `127.0.0.1:8080`, which is expanded to the tuple `(127, 0, 0, 1, 8080)`
+/// of the four octets followed by the port.
+///
+/// For example:
+/// ```
+/// let (a, b, c, d, port) = env_socketaddr!("BIND");
+/// ```
+#[inline_macro]
+pub fn env_socketaddr(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_socketaddr_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the numeric values of several environment variables as a tuple.
+///
+/// Each argument is a variable name, or a `(name, default)` pair whose numeric default is used if the variable is not set.
+/// Variables that are not set and have no default produce a diagnostic error listing all of them.
+///
+/// For example:
+/// ```
+/// let (workers, port) = env_all!("WORKERS", "PORT");
+/// let (workers, port) = env_all!("WORKERS", ("PORT", 8080));
+/// ```
+#[inline_macro]
+pub fn env_all(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_all_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the value of an environment variable holding a UUID as a `felt252`.
+///
+/// The value is a canonical UUID like `123e4567-e89b-12d3-a456-426614174000`,
+/// whose 16 bytes are packed big-endian into a numeric literal, or little-endian with `endian: little`.
+///
+/// For example:
+/// ```
+/// let trace_id: felt252 = env_uuid!("TRACE_ID");
+/// let trace_id: felt252 = env_uuid!("TRACE_ID", endian: little);
+/// ```
+#[inline_macro]
+pub fn env_uuid(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_uuid_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the value of an environment variable holding a hex color as a packed `felt252`.
+///
+/// The value is a color like `#1a2b3c` or `#1a2b3cff` with an alpha channel,
+/// which is expanded to the numeric literal of its hex digits.
+/// You can also specify a color string default value that will be returned if the environment variable is not set.
+///
+/// For example:
+/// ```
+/// let brand_color: u32 = env_color!("BRAND_COLOR");
+/// let brand_color: u32 = env_color!("BRAND_COLOR", "#ffffff");
+/// ```
+#[inline_macro]
+pub fn env_color(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_color_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the value of an environment variable holding a semantic version as a `(major, minor, patch)` tuple.
+///
+/// Pre-release and build suffixes after `-` or `+` are ignored, and missing components default to zero,
+/// so `1.2.3-rc.1` is expanded to `(1, 2, 3)` and `1.2` to `(1, 2, 0)`.
+///
+/// For example:
+/// ```
+/// let (major, minor, patch) = env_semver!("APP_VERSION");
+/// ```
+#[inline_macro]
+pub fn env_semver(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_semver_macro(token_stream, &BuildEnv))
+}
+
+/// Fails the build unless an environment variable equals the expected string.
+///
+/// Expands to the unit value `()` if the variable matches, e.g. to guard CI builds against the wrong configuration.
+///
+/// For example:
+/// ```
+/// env_assert_eq!("NETWORK", "mainnet");
+/// ```
+#[inline_macro]
+pub fn env_assert_eq(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_assert_eq_macro(token_stream, &BuildEnv))
+}
+
+/// Returns an integer field of an environment variable holding a JSON document as a `felt252`.
+///
+/// The field is selected with `path:`, nested fields and array elements are separated with dots.
+///
+/// For example:
+/// ```
+/// let workers: u32 = env_json!("CONFIG_JSON", path: "workers");
+/// let port: u16 = env_json!("CONFIG_JSON", path: "db.port");
+/// ```
+#[inline_macro]
+pub fn env_json(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_json_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the value of an environment variable holding a decimal number as a fixed-point `felt252`.
+///
+/// The decimal is multiplied by `10^scale`, where the scale is given as `scale:` and defaults to 18,
+/// so `PRICE=12.34` with `scale: 6` is expanded to `12340000`.
+///
+/// For example:
+/// ```
+/// let price: u128 = env_f64_fixed!("PRICE", scale: 6);
+/// ```
+#[inline_macro]
+pub fn env_f64_fixed(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_f64_fixed_macro(token_stream, &BuildEnv))
+}
+
+/// Declares a constant in a module for every environment variable starting with a prefix.
+///
+/// Variables are read from the process environment and the closest `.env` file, the process environment taking precedence.
+/// Constants are named after the variable with the prefix stripped, upper-cased, and are `felt252`.
+/// Variables whose names are not valid identifiers or whose values are not numeric, malformed or out of the `felt252` range
+/// are skipped with a warning. String values are skipped too, since Cairo constants can't hold a `ByteArray`.
+///
+/// For example, with `MYAPP_PORT=8080` and `MYAPP_WORKERS=4`:
+/// ```
+/// #[env_consts("MYAPP_")]
+/// mod config {}
+/// // mod config {
+/// //     const PORT: felt252 = 8080;
+/// //     const WORKERS: felt252 = 4;
+/// // }
+/// ```
+#[attribute_macro]
+pub fn env_consts(attr: TokenStream, item: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result_with_warnings(expand_env_consts_macro(attr, item, &BuildEnv))
+}
+
+/// Returns the bitwise OR of several environment variables holding numeric flags as a `felt252`.
+///
+/// Variables that are not set count as the value given as `default:`, which is 0 by default.
+///
+/// For example:
+/// ```
+/// let flags: felt252 = env_flags!("FLAG_A", "FLAG_B", default: 0);
+/// ```
+#[inline_macro]
+pub fn env_flags(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_flags_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the characters of an environment variable as an `Array<felt252>` of short strings.
+///
+/// Only ASCII values are supported, so that every character maps to a single short string.
+///
+/// For example, with `WORD=abc`:
+/// ```
+/// let chars: Array<felt252> = env_chars!("WORD");  // array!['a', 'b', 'c']
+/// ```
+#[inline_macro]
+pub fn env_chars(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_chars_macro(token_stream, &BuildEnv))
+}
+
+/// Returns a `Felt252Dict<felt252>` populated from an environment variable holding `key:value` pairs.
+///
+/// Pairs are separated with commas, keys are short strings and values are numbers,
+/// so `LIMITS=a:1,b:2` is expanded to a dictionary mapping `'a'` to 1 and `'b'` to 2.
+/// An empty value expands to an empty dictionary.
+///
+/// For example:
+/// ```
+/// let mut limits: Felt252Dict<felt252> = env_map!("LIMITS");
+/// ```
+#[inline_macro]
+pub fn env_map(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_map_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the value of an environment variable holding a filesystem path as a `ByteArray` string literal.
+///
+/// The path must exist when the macro is expanded, unless `must_exist: false` is given.
+/// Relative paths are resolved against the package directory, and the resolved path is returned.
+///
+/// For example:
+/// ```
+/// let asset_dir: ByteArray = env_path!("ASSET_DIR");
+/// let cache_dir: ByteArray = env_path!("CACHE_DIR", must_exist: false);
+/// ```
+#[inline_macro]
+pub fn env_path(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_path_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the index of the value of an environment variable in a list of variants as a `felt252`.
+///
+/// Indices start at zero, or at the value given as `start:`.
+///
+/// For example, with `STATE=active`:
+/// ```
+/// let state: felt252 = env_enum!("STATE", ["idle", "active", "done"]);  // 1
+/// let state: felt252 = env_enum!("STATE", ["idle", "active", "done"], start: 1);  // 2
+/// ```
+#[inline_macro]
+pub fn env_enum(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_enum_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the name of the first set environment variable among a list as a `ByteArray`, not its value.
+///
+/// You can also specify a default name that will be used if none of the variables is set.
+///
+/// For example, with only `STAGING_URL` set:
+/// ```
+/// let active: ByteArray = env_which!(["PROD_URL", "STAGING_URL", "DEV_URL"]);  // "STAGING_URL"
+/// let active: ByteArray = env_which!(["PROD_URL", "DEV_URL"], "none");  // "none"
+/// ```
+#[inline_macro]
+pub fn env_which(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_which_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the build time as a Unix timestamp in seconds, as a `felt252`.
+///
+/// For reproducible builds, the timestamp is read from `SOURCE_DATE_EPOCH` if it is set.
+///
+/// For example:
+/// ```
+/// let built_at: u64 = env_timestamp!();
+/// ```
+#[inline_macro]
+pub fn env_timestamp(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_timestamp_macro(token_stream, &BuildEnv))
+}
+
+/// Returns the value of an environment variable as a span of numbers, like `env_array!` but as read-only data.
+///
+/// The value is split on commas, or on the separator given as `sep:`, and each element is parsed as a number.
+/// An empty value expands to an empty span.
+///
+/// For example:
+/// ```
+/// let ids: Span<felt252> = env_span!("IDS");
+/// let ids: Span<felt252> = env_span!("IDS", sep: ";");
+/// ```
+#[inline_macro]
+pub fn env_span(token_stream: TokenStream) -> ProcMacroResult {
+    into_proc_macro_result(expand_env_span_macro(token_stream, &BuildEnv))
+}
+
+/// Converts the expansion result into a [`ProcMacroResult`], attaching the diagnostics on failure.
+fn into_proc_macro_result(result: Result<TokenStream, impl Into<Diagnostics>>) -> ProcMacroResult {
+    match result {
         Ok(token_stream) => ProcMacroResult::new(token_stream),
-        Err(diagnostic) => {
-            ProcMacroResult::new(TokenStream::empty()).with_diagnostics(diagnostic.into())
-        }
-    }
-}
-
-/// Expands the environment variable macro given the macro name, the expected type of the variable and the token stream.
-/// Returns the value of the environment variable as a token stream or a diagnostic error if the variable is not set or there were parsing errors.
-fn expand_env_macro(token_stream: impl ToString) -> Result<TokenStream, Diagnostic> {
-    let db = SimpleParserDatabase::default();
-    // Get the ExprInlineMacro object so we can use the helper functions.
-    let mac = parse_inline_macro("env!", token_stream, &db);
-    // Get the arguments of the macro. This macro expects a tuple as argument so we get the WrappedArgList::ParenthesizedArgList
-    let macro_args = if let WrappedArgList::ParenthesizedArgList(args) = mac.arguments(db.upcast())
-    {
-        args.arguments(db.upcast()).elements(db.upcast())
-    } else {
-        vec![]
-    };
-
-    if macro_args.len() == 0 {
-        return Err(Diagnostic::error("Please specify the environment variable name").into());
-    }
-
-    let env_var_name = get_env_variable_name(db.upcast(), &macro_args[0].arg_clause(db.upcast()))?;
-
-    match std::env::var(&env_var_name) {
-        Ok(val) => {
-            let numeric_val = BigInt::from_str(&val).map_err(|_| {
-                Diagnostic::error(&format!(
-                    "Failed to parse numeric environment variable: {}",
-                    val
-                ))
-                .into()
-            })?;
-            Ok(TokenStream::new(numeric_val.to_string()))
-        }
-        Err(_) => {
-            if macro_args.len() == 2 {
-                get_default_value(&db, &macro_args[1].arg_clause(db.upcast()))
-            } else {
-                Err(
-                    Diagnostic::error(&format!("Environment variable {} not set", env_var_name))
-                        .into(),
-                )
-            }
+        Err(diagnostics) => {
+            ProcMacroResult::new(TokenStream::empty()).with_diagnostics(diagnostics.into())
         }
     }
 }
 
-/// Returns an [`ExprInlineMacro`] from the text received.
-/// The expected text is the macro arguments.
-fn parse_inline_macro(
-    macro_name: &str,
-    token_stream: impl ToString,
-    db: &SimpleParserDatabase,
-) -> ExprInlineMacro {
-    // Create a virtual file that will be parsed.
-    let file = FileLongId::Virtual(VirtualFile {
-        parent: None,
-        name: "parser_input".into(),
-        content: format!("{}{}", macro_name, token_stream.to_string()).into(),
-        code_mappings: [].into(),
-        kind: FileKind::Expr,
-    })
-    .intern(db);
-
-    // Could fail if there was a parsing error but it shouldn't happen as the file has already
-    // been parsed once to reach this macro.
-    let node = db
-        .file_expr_syntax(file)
-        .expect("Failed to parse inline macro");
-
-    let Expr::InlineMacro(inline_macro) = node else {
-        unreachable!() // should not happen
-    };
-
-    inline_macro
-}
-
-/// Parses the second argument of the macro, which is the default value.
-/// Returns the default value as a token stream or a diagnostic error if there was a parsing error.
-fn get_default_value(
-    db: &SimpleParserDatabase,
-    arg_clause: &ArgClause,
-) -> Result<TokenStream, Diagnostic> {
-    let base_expr = match arg_clause {
-        ArgClause::Unnamed(arg_clause) => arg_clause.value(db.upcast()),
-        _ => return Err(Diagnostic::error("Expected unnamed default argument").into()),
-    };
-
-    if let Expr::Literal(base_lit) = base_expr {
-        let numeric_val = base_lit
-            .numeric_value(db.upcast())
-            .ok_or(Diagnostic::error("Failed to parse numeric default").into())?;
-        Ok(TokenStream::new(numeric_val.to_string()))
-    } else {
-        Err(Diagnostic::error("Expected numeric default").into())
-    }
-}
-
-/// Parses the first argument of the macro, which is the environment variable name.
-/// Returns the environment variable name as a string or a diagnostic error if the parsing failed.
-fn get_env_variable_name(
-    db: &SimpleParserDatabase,
-    arg_clause: &ArgClause,
-) -> Result<String, Diagnostic> {
-    let base_expr = match arg_clause {
-        ArgClause::Unnamed(arg_clause) => arg_clause.value(db.upcast()),
-        _ => return Err(Diagnostic::error("Expected unnamed argument").into()),
-    };
-
-    if let Expr::String(base_lit) = base_expr {
-        base_lit
-            .string_value(db.upcast())
-            .ok_or(Diagnostic::error("Failed to parse environment variable name").into())
-    } else {
-        Err(Diagnostic::error("Expected environment variable name").into())
+/// Converts the expansion result into a [`ProcMacroResult`], attaching the warnings on success and the diagnostics on failure.
+fn into_proc_macro_result_with_warnings(
+    result: Result<(TokenStream, Vec<Diagnostic>), impl Into<Diagnostics>>,
+) -> ProcMacroResult {
+    match result {
+        Ok((token_stream, warnings)) => {
+            ProcMacroResult::new(token_stream).with_diagnostics(warnings.into())
+        }
+        Err(diagnostics) => into_proc_macro_result(Err(diagnostics)),
+    }
+}
+
+/// Sources the macros resolve variables from.
+/// The macros read the build environment through [`BuildEnv`], while tests inject fixed variables,
+/// so that expansions depend neither on the process environment nor on the filesystem.
+trait Env {
+    /// Looks up a variable in the process environment, like [`std::env::var`].
+    fn var(&self, name: &str) -> Result<String, VarError>;
+
+    /// Returns all the variables of the process environment, skipping those that are not valid Unicode.
+    fn vars(&self) -> Vec<(String, String)>;
+
+    /// Returns the variables of the closest `.env` file, or the error describing why it could not be parsed.
+    fn dotenv(&self) -> &DotenvVars;
+
+    /// Looks up a variable in the `[tool.env]` table of the package manifest.
+    fn manifest_var(&self, name: &str) -> Option<String>;
+
+    /// Returns the directory of the package manifest, which relative paths given to the macros are resolved against.
+    fn package_dir(&self) -> Option<PathBuf>;
+}
+
+/// The environment of the build: the process environment, the closest `.env` file and the package manifest.
+struct BuildEnv;
+
+impl Env for BuildEnv {
+    /// Lookups are memoized for the lifetime of the process, so repeated lookups of the same variable
+    /// are cheap and resolve consistently within a build. Missing variables are cached as not set.
+    /// The cache is never cleared: a long-lived host loading the macro, like the language server,
+    /// doesn't observe changes to the environment until it is restarted.
+    fn var(&self, name: &str) -> Result<String, VarError> {
+        static CACHE: OnceLock<Mutex<HashMap<String, Result<String, VarError>>>> = OnceLock::new();
+
+        let mut cache = CACHE
+            .get_or_init(Default::default)
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner);
+        cache
+            .entry(name.to_string())
+            .or_insert_with(|| std::env::var(name))
+            .clone()
+    }
+
+    fn vars(&self) -> Vec<(String, String)> {
+        std::env::vars_os()
+            .filter_map(|(name, val)| Some((name.into_string().ok()?, val.into_string().ok()?)))
+            .collect()
+    }
+
+    fn dotenv(&self) -> &DotenvVars {
+        dotenv::closest_dotenv()
+    }
+
+    fn manifest_var(&self, name: &str) -> Option<String> {
+        manifest::manifest_var(name)
+    }
+
+    fn package_dir(&self) -> Option<PathBuf> {
+        std::env::var_os("SCARB_MANIFEST_PATH")
+            .map(PathBuf::from)
+            .and_then(|manifest_path| manifest_path.parent().map(Path::to_path_buf))
+    }
+}
+
+/// Looks up the value of an environment variable, falling back to the closest `.env` file.
+/// Returns a diagnostic error if the variable is set to a value that is not valid UTF-8,
+/// or if it is not set in the process environment and the `.env` file can't be parsed.
+fn lookup_env_var(env: &impl Env, name: &str) -> Result<Option<String>, Diagnostic> {
+    match env.var(name) {
+        Ok(val) => Ok(Some(val)),
+        Err(VarError::NotUnicode(_)) => Err(Diagnostic::error(non_unicode_message(name))),
+        Err(VarError::NotPresent) => dotenv_var(env, name),
+    }
+}
+
+/// Looks up a variable in the closest `.env` file.
+/// Returns a diagnostic error including the path of the file if it can't be parsed.
+fn dotenv_var(env: &impl Env, name: &str) -> Result<Option<String>, Diagnostic> {
+    match env.dotenv() {
+        Ok(dotenv_vars) => Ok(dotenv_vars.get(name).cloned()),
+        Err(err) => Err(Diagnostic::error(format!(
+            "Failed to parse .env file {}",
+            err
+        ))),
+    }
+}
+
+/// Resolves a path given to a macro against the package directory, unless it is absolute or the directory is unknown.
+fn package_path(env: &impl Env, path: &str) -> PathBuf {
+    match env.package_dir() {
+        Some(package_dir) => package_dir.join(path),
+        None => PathBuf::from(path),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsString;
+
+    use num_bigint::BigInt;
+
+    use super::*;
+    use crate::resolve::{resolution_log_line, stark_prime};
+    use crate::value::strip_digit_separators;
+
+    /// Environment with fixed variables, so that expansions don't depend on the process running the tests.
+    struct TestEnv {
+        vars: HashMap<String, OsString>,
+        dotenv: DotenvVars,
+        manifest: HashMap<String, String>,
+        package_dir: Option<PathBuf>,
+    }
+
+    impl TestEnv {
+        /// Creates an environment with the given process variables, no `.env` file and no manifest.
+        fn new(vars: &[(&str, &str)]) -> Self {
+            TestEnv {
+                vars: vars
+                    .iter()
+                    .map(|(name, val)| (name.to_string(), OsString::from(val)))
+                    .collect(),
+                dotenv: Ok(HashMap::new()),
+                manifest: HashMap::new(),
+                package_dir: None,
+            }
+        }
+
+        /// Sets the variables of the `.env` file.
+        fn with_dotenv(mut self, vars: &[(&str, &str)]) -> Self {
+            self.dotenv = Ok(vars
+                .iter()
+                .map(|(name, val)| (name.to_string(), val.to_string()))
+                .collect());
+            self
+        }
+    }
+
+    impl Env for TestEnv {
+        fn var(&self, name: &str) -> Result<String, VarError> {
+            let val = self.vars.get(name).ok_or(VarError::NotPresent)?;
+            val.to_str()
+                .map(str::to_string)
+                .ok_or_else(|| VarError::NotUnicode(val.clone()))
+        }
+
+        fn vars(&self) -> Vec<(String, String)> {
+            self.vars
+                .iter()
+                .filter_map(|(name, val)| Some((name.clone(), val.to_str()?.to_string())))
+                .collect()
+        }
+
+        fn dotenv(&self) -> &DotenvVars {
+            &self.dotenv
+        }
+
+        fn manifest_var(&self, name: &str) -> Option<String> {
+            self.manifest.get(name).cloned()
+        }
+
+        fn package_dir(&self) -> Option<PathBuf> {
+            self.package_dir.clone()
+        }
+    }
+
+    /// Returns the messages of the diagnostics.
+    fn messages(diagnostics: impl Into<Diagnostics>) -> Vec<String> {
+        diagnostics
+            .into()
+            .into_iter()
+            .map(|diagnostic| diagnostic.message)
+            .collect()
+    }
+
+    /// Expands `env!` with the given arguments, returning the expansion or the error messages.
+    fn expand(env: &TestEnv, args: &str) -> Result<String, Vec<String>> {
+        expand_env_macro(format!("({})", args), env)
+            .map(|(token_stream, _)| token_stream.to_string())
+            .map_err(messages)
+    }
+
+    /// Expands `env_bool!` with the given arguments, returning the expansion or the error messages.
+    fn expand_bool(env: &TestEnv, args: &str) -> Result<String, Vec<String>> {
+        expand_env_bool_macro(format!("({})", args), env)
+            .map(|token_stream| token_stream.to_string())
+            .map_err(messages)
+    }
+
+    /// Expands `env!` with the given arguments, returning the first error message.
+    fn expand_err(env: &TestEnv, args: &str) -> String {
+        expand(env, args).unwrap_err().remove(0)
+    }
+
+    /// Expands `env!` with the given arguments, returning the first line of every error message, without the notes.
+    fn expand_errs(env: &TestEnv, args: &str) -> Vec<String> {
+        expand(env, args)
+            .unwrap_err()
+            .iter()
+            .map(|message| message.lines().next().unwrap_or_default().to_string())
+            .collect()
+    }
+
+    /// Asserts that expanding `env!` with the given arguments fails with an error starting with `expected`.
+    #[track_caller]
+    fn assert_expand_err(env: &TestEnv, args: &str, expected: &str) {
+        let err = expand_err(env, args);
+        assert!(
+            err.starts_with(expected),
+            "{:?} doesn't start with {:?}",
+            err,
+            expected
+        );
+    }
+
+    /// Expands a macro other than `env!` with the given arguments, returning the expansion or the first error message.
+    fn expand_macro<E: Into<Diagnostics>>(
+        expand_macro: fn(String, &TestEnv) -> Result<TokenStream, E>,
+        env: &TestEnv,
+        args: &str,
+    ) -> Result<String, String> {
+        expand_macro(format!("({})", args), env)
+            .map(|token_stream| token_stream.to_string())
+            .map_err(|err| messages(err).remove(0))
+    }
+
+    #[test]
+    fn expands_numeric_values() {
+        let env = TestEnv::new(&[("PORT", "8080"), ("OFFSET", "-5"), ("MASK", "0xff")]);
+        assert_eq!(expand(&env, r#""PORT""#).unwrap(), "8080");
+        assert_eq!(expand(&env, r#""OFFSET""#).unwrap(), "-5");
+        assert_eq!(expand(&env, r#""MASK""#).unwrap(), "255");
+    }
+
+    #[test]
+    fn expands_string_values() {
+        let env = TestEnv::new(&[("NAME", "env_macro")]);
+        assert_eq!(expand(&env, r#""NAME""#).unwrap(), r#""env_macro""#);
+    }
+
+    #[test]
+    fn expands_default_if_not_set() {
+        let env = TestEnv::new(&[("PORT", "8080")]);
+        assert_eq!(expand(&env, r#""PORT", 1"#).unwrap(), "8080");
+        assert_eq!(expand(&env, r#""WORKERS", 4"#).unwrap(), "4");
+        assert_eq!(
+            expand(&env, r#""NAME", default: "anon""#).unwrap(),
+            r#""anon""#
+        );
+    }
+
+    #[test]
+    fn fails_if_not_set_without_default() {
+        let env = TestEnv::new(&[]);
+        assert_expand_err(&env, r#""PORT""#, "Environment variable PORT not set");
+        let err = expand_err(&env, r#""PORT""#);
+        assert!(err.contains(r#"help: set PORT or pass a default like env!("PORT", 0)"#));
+    }
+
+    #[test]
+    fn quotes_the_offending_argument() {
+        let env = TestEnv::new(&[]);
+        assert_eq!(
+            expand_err(&env, r#""PORT""#).lines().nth(1),
+            Some(r#"note: in `"PORT"`"#)
+        );
+        assert_eq!(
+            expand_err(&env, "123"),
+            "Expected environment variable name\nnote: in `123`"
+        );
+        assert_eq!(
+            expand_err(&env, r#""PORT", 1, 2"#).lines().last(),
+            Some("note: in `2`")
+        );
+    }
+
+    #[test]
+    fn fails_on_syntax_errors() {
+        let env = TestEnv::new(&[("X", "1")]);
+        assert_eq!(expand(&env, r#""X", 1,"#).unwrap(), "1");
+        for token_stream in [r#"("X""#, r#"("X" 1 2)"#, r#"("X", default: )"#] {
+            let err = messages(expand_env_macro(token_stream, &env).unwrap_err()).remove(0);
+            assert!(err.starts_with("Could not parse env!"), "{}", err);
+            assert!(err.contains("error: "), "{}", err);
+        }
+    }
+
+    /// Expands `env_with_flag!` with the given arguments, returning the expansion or the error messages.
+    fn expand_with_flag(env: &TestEnv, args: &str) -> Result<String, Vec<String>> {
+        expand_env_with_flag_macro(format!("({})", args), env)
+            .map(|(token_stream, _)| token_stream.to_string())
+            .map_err(messages)
+    }
+
+    #[test]
+    fn expands_value_with_flag() {
+        let env = TestEnv::new(&[("PORT", "8080")]);
+        assert_eq!(
+            expand_with_flag(&env, r#""PORT", 0"#).unwrap(),
+            "(8080, true)"
+        );
+        assert_eq!(
+            expand_with_flag(&env, r#""WORKERS", 0"#).unwrap(),
+            "(0, false)"
+        );
+        assert_eq!(
+            expand_with_flag(&env, r#""PORT", "HOST", join: ":", default: "localhost""#).unwrap(),
+            r#"("localhost", false)"#
+        );
+    }
+
+    #[test]
+    fn names_the_called_macro_in_errors() {
+        let env = TestEnv::new(&[("HOST", "localhost")]);
+        let err = expand_with_flag(&env, r#""PORT""#).unwrap_err().remove(0);
+        assert!(
+            err.contains(r#"pass a default like env_with_flag!("PORT", 0)"#),
+            "{}",
+            err
+        );
+        let err = expand_with_flag(&env, r#""PORT", 1, 2"#)
+            .unwrap_err()
+            .remove(0);
+        assert!(
+            err.starts_with("env_with_flag! accepts at most a name and a default"),
+            "{}",
+            err
+        );
+        let err = expand_with_flag(&env, r#""HOST", "PORT", join: ":""#)
+            .unwrap_err()
+            .remove(0);
+        assert!(
+            err.contains(r#"pass a default like env_with_flag!("PORT", default: "")"#),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn fails_on_malformed_numbers() {
+        let env = TestEnv::new(&[("PORT", "0x80zz")]);
+        assert!(expand(&env, r#""PORT""#).is_err());
+    }
+
+    #[test]
+    fn resolves_dotenv_after_environment() {
+        let env = TestEnv::new(&[("PORT", "8080")])
+            .with_dotenv(&[("PORT", "9090"), ("HOST", "localhost")]);
+        assert_eq!(expand(&env, r#""PORT""#).unwrap(), "8080");
+        assert_eq!(expand(&env, r#""HOST""#).unwrap(), r#""localhost""#);
+    }
+
+    #[test]
+    fn reports_malformed_dotenv_only_if_consulted() {
+        let mut env = TestEnv::new(&[("PORT", "8080")]);
+        env.dotenv = Err(".env: unterminated quoted value of KEY starting at line 1".to_string());
+        assert_eq!(expand(&env, r#""PORT""#).unwrap(), "8080");
+        assert_eq!(
+            expand_bool(&env, r#""PORT_SET", false"#).map_err(|err| err[0].clone()),
+            Err("Failed to parse .env file .env: unterminated quoted value of KEY starting at line 1".to_string())
+        );
+        assert_expand_err(
+            &env,
+            r#""HOST", default: "localhost""#,
+            "Failed to parse .env file .env",
+        );
+        assert_expand_err(
+            &env,
+            r#""PORT", prefer: dotenv"#,
+            "Failed to parse .env file .env",
+        );
+    }
+
+    /// The STARK field prime, the smallest value that doesn't fit into `felt252`.
+    const PRIME: &str =
+        "3618502788666131213697322783095070105623107215331596699973092056135872020481";
+
+    #[test]
+    fn checks_felt_range_of_optional_and_list_values() {
+        let below_prime = stark_prime() - BigInt::from(1);
+        let env = TestEnv::new(&[
+            ("BELOW", &below_prime.to_string()),
+            ("ABOVE", PRIME),
+            ("LIST", &format!("1, {}", below_prime)),
+            ("LIST_ABOVE", &format!("1, {}", PRIME)),
+        ]);
+        let expand_with =
+            |expand_macro: fn(String, &TestEnv) -> Result<TokenStream, Diagnostic>, args: &str| {
+                expand_macro(format!("({})", args), &env)
+                    .map(|token_stream| token_stream.to_string())
+                    .map_err(|err| err.message)
+            };
+        assert_eq!(
+            expand_with(expand_option_env_macro, r#""BELOW""#).unwrap(),
+            format!("Option::Some({})", below_prime)
+        );
+        assert!(expand_with(expand_option_env_macro, r#""ABOVE""#)
+            .unwrap_err()
+            .contains("does not fit into felt252"));
+        assert_eq!(
+            expand_with(expand_env_array_macro, r#""LIST""#).unwrap(),
+            format!("array![1, {}]", below_prime)
+        );
+        for expand_macro in [expand_env_array_macro, expand_env_span_macro] {
+            assert!(expand_with(expand_macro, r#""LIST_ABOVE""#)
+                .unwrap_err()
+                .contains("does not fit into felt252"));
+        }
+    }
+
+    #[test]
+    fn expands_binary_and_octal_values() {
+        let env = TestEnv::new(&[
+            ("BIN", "0b1010"),
+            ("BIN_UPPER", "0B11"),
+            ("OCT", "0o777"),
+            ("OCT_UPPER", "0O17"),
+            ("BAD_BIN", "0b1021"),
+            ("BAD_OCT", "0o78"),
+        ]);
+        assert_eq!(expand(&env, r#""BIN""#).unwrap(), "10");
+        assert_eq!(expand(&env, r#""BIN_UPPER""#).unwrap(), "3");
+        assert_eq!(expand(&env, r#""OCT""#).unwrap(), "511");
+        assert_eq!(expand(&env, r#""OCT_UPPER""#).unwrap(), "15");
+        assert_expand_err(
+            &env,
+            r#""BAD_BIN""#,
+            "Failed to parse numeric value of environment variable BAD_BIN: 0b1021",
+        );
+        assert_expand_err(
+            &env,
+            r#""BAD_OCT""#,
+            "Failed to parse numeric value of environment variable BAD_OCT: 0o78",
+        );
+    }
+
+    #[test]
+    fn expands_negative_values() {
+        let env = TestEnv::new(&[("OFFSET", "-5")]);
+        assert_eq!(expand(&env, r#""OFFSET""#).unwrap(), "-5");
+        assert_eq!(expand(&env, r#""UNSET", -5"#).unwrap(), "-5");
+        assert_eq!(expand(&env, r#""OFFSET", signed: i32"#).unwrap(), "-5");
+    }
+
+    #[test]
+    fn expands_optional_values() {
+        let env = TestEnv::new(&[("LEVEL", "3"), ("NAME", "env_macro")]);
+        assert_eq!(
+            expand_macro(expand_option_env_macro, &env, r#""LEVEL""#).unwrap(),
+            "Option::Some(3)"
+        );
+        assert_eq!(
+            expand_macro(expand_option_env_macro, &env, r#""NAME""#).unwrap(),
+            r#"Option::Some("env_macro")"#
+        );
+        assert_eq!(
+            expand_macro(expand_option_env_macro, &env, r#""UNSET""#).unwrap(),
+            "Option::None"
+        );
+        assert_eq!(
+            expand_macro(expand_option_env_macro, &env, r#""LEVEL", 1"#).unwrap_err(),
+            "option_env! accepts only the environment variable name"
+        );
+    }
+
+    #[test]
+    fn expands_string_defaults() {
+        let env = TestEnv::new(&[("VERSION", "2")]);
+        assert_eq!(
+            expand(&env, r#""UNSET", default: "fallback""#).unwrap(),
+            r#""fallback""#
+        );
+        assert_eq!(
+            expand(&env, r#""UNSET", default: "say \"hi\"""#).unwrap(),
+            r#""say \"hi\"""#
+        );
+        assert_eq!(expand(&env, r#""VERSION", default: "dev""#).unwrap(), "2");
+    }
+
+    #[test]
+    fn resolves_first_set_name() {
+        let first = TestEnv::new(&[("APP_PORT", "8080"), ("PORT", "9090")]);
+        assert_eq!(
+            expand(&first, r#""APP_PORT", "PORT", default: 3000"#).unwrap(),
+            "8080"
+        );
+        let second = TestEnv::new(&[("PORT", "9090")]);
+        assert_eq!(
+            expand(&second, r#""APP_PORT", "PORT", default: 3000"#).unwrap(),
+            "9090"
+        );
+        let none = TestEnv::new(&[]);
+        assert_eq!(
+            expand(&none, r#""APP_PORT", "PORT", default: 3000"#).unwrap(),
+            "3000"
+        );
+        assert_expand_err(
+            &none,
+            r#""APP_PORT", "PORT""#,
+            "None of the environment variables APP_PORT, PORT are set",
+        );
+    }
+
+    #[test]
+    fn trims_numbers_and_optionally_strings() {
+        let env = TestEnv::new(&[("COUNT", "\n42\n"), ("NAME", "  foo  ")]);
+        assert_eq!(expand(&env, r#""COUNT""#).unwrap(), "42");
+        assert_eq!(expand(&env, r#""NAME""#).unwrap(), r#""  foo  ""#);
+        assert_eq!(expand(&env, r#""NAME", trim: true"#).unwrap(), r#""foo""#);
+    }
+
+    #[test]
+    fn checks_inclusive_bounds() {
+        let env = TestEnv::new(&[("LOW", "0"), ("HIGH", "128"), ("MIN", "1"), ("MAX", "64")]);
+        let bounds = "min: 1, max: 64";
+        assert_expand_err(
+            &env,
+            &format!(r#""LOW", {}"#, bounds),
+            "LOW=0 is below min 1",
+        );
+        assert_expand_err(
+            &env,
+            &format!(r#""HIGH", {}"#, bounds),
+            "HIGH=128 exceeds max 64",
+        );
+        assert_eq!(expand(&env, &format!(r#""MIN", {}"#, bounds)).unwrap(), "1");
+        assert_eq!(
+            expand(&env, &format!(r#""MAX", {}"#, bounds)).unwrap(),
+            "64"
+        );
+        assert_expand_err(&env, r#""UNSET", 100, max: 64"#, "UNSET=100 exceeds max 64");
+    }
+
+    #[test]
+    fn packs_short_strings() {
+        let env = TestEnv::new(&[
+            ("SYMBOL", "STRK"),
+            ("LONG", &"a".repeat(32)),
+            ("EMOJI", "é"),
+        ]);
+        assert_eq!(
+            expand_macro(expand_env_shortstring_macro, &env, r#""SYMBOL""#).unwrap(),
+            "1398035019"
+        );
+        assert_eq!(
+            expand_macro(expand_env_shortstring_macro, &env, r#""UNSET", 'ab'"#).unwrap(),
+            "24930"
+        );
+        assert!(
+            expand_macro(expand_env_shortstring_macro, &env, r#""LONG""#)
+                .unwrap_err()
+                .contains("it is 32 characters long, at most 31 are supported")
+        );
+        assert!(
+            expand_macro(expand_env_shortstring_macro, &env, r#""EMOJI""#)
+                .unwrap_err()
+                .contains("only ASCII characters are supported")
+        );
+    }
+
+    #[test]
+    fn names_the_variable_in_numeric_errors() {
+        let long_value = format!("0x{}", "z".repeat(80));
+        let env = TestEnv::new(&[("PORT", "0x80zz"), ("LONG", &long_value)]);
+        assert_eq!(
+            expand_errs(&env, r#""PORT""#)[0],
+            "Failed to parse numeric value of environment variable PORT: 0x80zz (invalid digit `z` at position 5)"
+        );
+        assert!(expand_errs(&env, r#""LONG""#)[0].starts_with(&format!(
+            "Failed to parse numeric value of environment variable LONG: {}...",
+            &long_value[..64]
+        )));
+    }
+
+    #[test]
+    fn expands_expression_defaults_verbatim() {
+        let env = TestEnv::new(&[("TIMEOUT", "10")]);
+        assert_eq!(
+            expand(&env, "\"UNSET\", DEFAULT_TIMEOUT").unwrap(),
+            "DEFAULT_TIMEOUT"
+        );
+        assert_eq!(
+            expand(&env, "\"UNSET\", config::TIMEOUT").unwrap(),
+            "config::TIMEOUT"
+        );
+        assert_eq!(expand(&env, "\"TIMEOUT\", DEFAULT_TIMEOUT").unwrap(), "10");
+    }
+
+    #[test]
+    fn rejects_extra_positional_arguments() {
+        let env = TestEnv::new(&[("X", "1")]);
+        assert_expand_err(
+            &env,
+            r#""X", 1, 2"#,
+            "env! accepts at most a name and a default, got 3 positional arguments",
+        );
+        assert_expand_err(
+            &env,
+            r#""X", "Y", 1, 2"#,
+            "env! accepts at most a name and a default, got 4 positional arguments",
+        );
+    }
+
+    #[test]
+    fn accepts_every_delimiter() {
+        let env = TestEnv::new(&[("X", "1")]);
+        for token_stream in [r#"("X")"#, r#"["X"]"#, r#"{"X"}"#] {
+            let (expansion, _) = expand_env_macro(token_stream, &env).unwrap();
+            assert_eq!(expansion.to_string(), "1");
+        }
+        for token_stream in ["()", "[]", "{}"] {
+            let err = messages(expand_env_macro(token_stream, &env).unwrap_err()).remove(0);
+            assert_eq!(err, "Please specify the environment variable name");
+        }
+    }
+
+    #[test]
+    fn retries_lookup_without_prefix() {
+        let prefixed = TestEnv::new(&[("MYAPP_PORT", "8080"), ("PORT", "9090")]);
+        assert_eq!(
+            expand(&prefixed, r#""MYAPP_PORT", strip_prefix: "MYAPP_""#).unwrap(),
+            "8080"
+        );
+        let stripped = TestEnv::new(&[("PORT", "9090")]);
+        assert_eq!(
+            expand(&stripped, r#""MYAPP_PORT", strip_prefix: "MYAPP_""#).unwrap(),
+            "9090"
+        );
+        let none = TestEnv::new(&[]);
+        assert_eq!(
+            expand(&none, r#""MYAPP_PORT", 1, strip_prefix: "MYAPP_""#).unwrap(),
+            "1"
+        );
+        assert_expand_err(
+            &none,
+            r#""MYAPP_PORT", strip_prefix: "MYAPP_""#,
+            "None of the environment variables MYAPP_PORT, PORT are set",
+        );
+    }
+
+    #[test]
+    fn expands_requested_kind() {
+        let sha = "0123456789abcdef0123456789abcdef01234567";
+        let env = TestEnv::new(&[("SHA", sha), ("COUNT", "42"), ("FLAG", "yes")]);
+        assert_eq!(
+            expand(&env, r#""SHA", kind: bytearray"#).unwrap(),
+            format!("\"{}\"", sha)
+        );
+        assert_eq!(
+            expand(&env, r#""COUNT", kind: bytearray"#).unwrap(),
+            r#""42""#
+        );
+        assert_eq!(expand(&env, r#""COUNT", kind: felt"#).unwrap(), "42");
+        assert_eq!(expand(&env, r#""COUNT", kind: hex"#).unwrap(), "0x2a");
+        assert_eq!(expand(&env, r#""FLAG", kind: bool"#).unwrap(), "true");
+        assert_expand_err(
+            &env,
+            r#""COUNT", kind: u32"#,
+            "Unsupported kind: u32, expected one of felt, bytearray, bool, hex",
+        );
+    }
+
+    #[test]
+    fn truncates_long_short_string_values() {
+        let env = TestEnv::new(&[("LONG", &"a".repeat(80)), ("WIDE", &"é".repeat(80))]);
+        let expand_shortstring = |args| expand_macro(expand_env_shortstring_macro, &env, args);
+        assert_eq!(
+            expand_shortstring(r#""LONG""#).unwrap_err(),
+            format!(
+                "LONG={}... is not a valid short string: it is 80 characters long, at most 31 are supported",
+                "a".repeat(64)
+            )
+        );
+        assert_eq!(
+            expand_shortstring(r#""WIDE""#).unwrap_err(),
+            format!(
+                "WIDE={}... is not a valid short string: only ASCII characters are supported, pass utf8: true to pack UTF-8 bytes",
+                "é".repeat(64)
+            )
+        );
+    }
+
+    #[test]
+    fn expands_bool_values_and_defaults() {
+        let env = TestEnv::new(&[("DEBUG", " Yes "), ("VERBOSE", "off"), ("BAD", "maybe")]);
+        assert_eq!(expand_bool(&env, r#""DEBUG""#).unwrap(), "true");
+        assert_eq!(expand_bool(&env, r#""VERBOSE", true"#).unwrap(), "false");
+        assert_eq!(expand_bool(&env, r#""UNSET", true"#).unwrap(), "true");
+        assert_eq!(expand_bool(&env, r#""UNSET", false"#).unwrap(), "false");
+        assert_eq!(
+            expand_bool(&env, r#""BAD", false"#).unwrap_err()[0],
+            "Failed to parse boolean value of environment variable BAD: maybe (expected one of true/false, 1/0, yes/no, on/off)"
+        );
+        assert_eq!(
+            expand_bool(&env, r#""UNSET""#).unwrap_err()[0],
+            "Environment variable UNSET not set\nnote: in `\"UNSET\"`\nhelp: set UNSET or pass a default like env_bool!(\"UNSET\", false)"
+        );
+        assert!(expand_bool(&env, r#""UNSET", 1"#).unwrap_err()[0]
+            .starts_with("Expected boolean default\nnote: in `1`"));
+        assert!(
+            expand_bool(&env, r#""UNSET", default: true"#).unwrap_err()[0]
+                .starts_with("Expected unnamed default argument")
+        );
+    }
+
+    #[test]
+    fn expands_u256_values() {
+        let env = TestEnv::new(&[
+            ("SUPPLY", "0x100000000000000000000000000000002"),
+            ("NEG", "-1"),
+        ]);
+        let expand_u256 = |args| expand_macro(expand_env_u256_macro, &env, args);
+        assert_eq!(
+            expand_u256(r#""SUPPLY""#).unwrap(),
+            "u256 { low: 2, high: 1 }"
+        );
+        assert_eq!(
+            expand_u256(r#""UNSET", 5"#).unwrap(),
+            "u256 { low: 5, high: 0 }"
+        );
+        assert_eq!(
+            expand_u256(r#""NEG""#).unwrap_err(),
+            "NEG=-1 does not fit into u256"
+        );
+        assert_eq!(
+            expand_u256(r#""UNSET", 5, 6"#).unwrap_err(),
+            "Unexpected argument\nnote: in `6`"
+        );
+    }
+
+    #[test]
+    fn rejects_misplaced_digit_separators() {
+        let env = TestEnv::new(&[
+            ("SEPARATED", "1_000"),
+            ("DOUBLED", "1__000"),
+            ("LEADING", "_100"),
+            ("TRAILING", "100_"),
+        ]);
+        assert_eq!(expand(&env, r#""SEPARATED""#).unwrap(), "1000");
+        assert_eq!(strip_digit_separators("1_000", 10).as_deref(), Some("1000"));
+        for val in ["1__000", "_100", "100_"] {
+            assert_eq!(strip_digit_separators(val, 10), None, "{}", val);
+        }
+        assert_expand_err(
+            &env,
+            r#""DOUBLED""#,
+            "Failed to parse numeric value of environment variable DOUBLED: 1__000 (misplaced digit separator at position 2)",
+        );
+        assert_expand_err(
+            &env,
+            r#""LEADING""#,
+            "Failed to parse numeric value of environment variable LEADING: _100 (misplaced digit separator at position 1)",
+        );
+        assert_expand_err(
+            &env,
+            r#""TRAILING""#,
+            "Failed to parse numeric value of environment variable TRAILING: 100_ (misplaced digit separator at position 4)",
+        );
+    }
+
+    /// Creates an empty directory for a test under the temporary directory.
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("env_macro_{}_{}", std::process::id(), name));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn reads_explicit_dotenv_file() {
+        let dir = test_dir("explicit_dotenv");
+        std::fs::create_dir_all(dir.join("config")).unwrap();
+        std::fs::write(dir.join("config/prod.env"), "PORT=8080\n").unwrap();
+        let mut env = TestEnv::new(&[]).with_dotenv(&[("PORT", "9090")]);
+        env.package_dir = Some(dir);
+        assert_eq!(
+            expand(&env, r#""PORT", dotenv: "config/prod.env""#).unwrap(),
+            "8080"
+        );
+        assert_eq!(expand(&env, r#""PORT""#).unwrap(), "9090");
+        assert!(expand_err(&env, r#""PORT", dotenv: "config/missing.env""#)
+            .starts_with("Failed to read dotenv file config/missing.env: "));
+    }
+
+    #[test]
+    fn expands_durations_in_seconds() {
+        let env = TestEnv::new(&[
+            ("BARE", "45"),
+            ("SECONDS", "30s"),
+            ("MINUTES", "5m"),
+            ("HOURS", "1h"),
+            ("DAYS", "2d"),
+            ("WEEKS", "1w"),
+            ("NEGATIVE", "-5m"),
+        ]);
+        let expand_duration = |args| expand_macro(expand_env_duration_macro, &env, args);
+        assert_eq!(expand_duration(r#""BARE""#).unwrap(), "45");
+        assert_eq!(expand_duration(r#""SECONDS""#).unwrap(), "30");
+        assert_eq!(expand_duration(r#""MINUTES""#).unwrap(), "300");
+        assert_eq!(expand_duration(r#""HOURS""#).unwrap(), "3600");
+        assert_eq!(expand_duration(r#""DAYS""#).unwrap(), "172800");
+        assert_eq!(expand_duration(r#""UNSET", 1800"#).unwrap(), "1800");
+        assert_eq!(
+            expand_duration(r#""UNSET", 1800, 60"#).unwrap_err(),
+            "Unexpected argument\nnote: in `60`"
+        );
+        assert_eq!(
+            expand_duration(r#""WEEKS""#).unwrap_err(),
+            "Unrecognized duration unit w in environment variable WEEKS, expected one of s, m, h, d"
+        );
+        assert_eq!(
+            expand_duration(r#""NEGATIVE""#).unwrap_err(),
+            "Duration of environment variable NEGATIVE must not be negative: -5m"
+        );
+    }
+
+    #[test]
+    fn expands_percentages_in_basis_points() {
+        let env = TestEnv::new(&[
+            ("FEE", "2.5%"),
+            ("FULL", "100%"),
+            ("BARE", "0.05"),
+            ("OVER", "100.01%"),
+            ("PRECISE", "1.234%"),
+        ]);
+        let expand_bps = |args| expand_macro(expand_env_bps_macro, &env, args);
+        assert_eq!(expand_bps(r#""FEE""#).unwrap(), "250");
+        assert_eq!(expand_bps(r#""FULL""#).unwrap(), "10000");
+        assert_eq!(expand_bps(r#""BARE""#).unwrap(), "5");
+        assert_eq!(expand_bps(r#""UNSET", 30"#).unwrap(), "30");
+        assert_eq!(expand_bps(r#""UNSET", 10000"#).unwrap(), "10000");
+        assert_eq!(
+            expand_bps(r#""UNSET", 10001"#).unwrap_err(),
+            "Default of UNSET is not between 0 and 10000 basis points: 10001\nnote: in `10001`"
+        );
+        assert_eq!(
+            expand_bps(r#""UNSET", -1"#).unwrap_err(),
+            "Default of UNSET is not between 0 and 10000 basis points: -1\nnote: in `-1`"
+        );
+        assert_eq!(
+            expand_bps(r#""UNSET", 30, 40"#).unwrap_err(),
+            "Unexpected argument\nnote: in `40`"
+        );
+        assert_eq!(
+            expand_bps(r#""OVER""#).unwrap_err(),
+            "OVER=100.01% exceeds 100%"
+        );
+        assert_eq!(
+            expand_bps(r#""PRECISE""#).unwrap_err(),
+            "Percentage of environment variable PRECISE has more than two decimal places: 1.234%"
+        );
+    }
+
+    #[test]
+    fn decodes_base64_bytes() {
+        let env = TestEnv::new(&[("KEY", "3q2+7w=="), ("TEXT", "aGk="), ("BAD", "3q2+7w=")]);
+        let expand_bytes = |args| expand_macro(expand_env_bytes_macro, &env, args);
+        assert_eq!(
+            expand_bytes(r#""KEY""#).unwrap(),
+            "array![0xde, 0xad, 0xbe, 0xef]"
+        );
+        assert_eq!(
+            expand_bytes(r#""TEXT", kind: bytearray"#).unwrap(),
+            r#""hi""#
+        );
+        assert!(expand_bytes(r#""BAD""#)
+            .unwrap_err()
+            .starts_with("Failed to decode base64 value of environment variable BAD: "));
+    }
+
+    #[test]
+    fn interpolates_references() {
+        let env = TestEnv::new(&[
+            ("URL", "https://${HOST}:${PORT}"),
+            ("HOST", "example.com"),
+            ("PORT", "8080"),
+            ("BROKEN", "${MISSING}/api"),
+            ("A", "${B}"),
+            ("B", "${A}"),
+        ]);
+        assert_eq!(
+            expand(&env, r#""URL", interpolate: true"#).unwrap(),
+            r#""https://example.com:8080""#
+        );
+        assert_eq!(
+            expand(&env, r#""URL""#).unwrap(),
+            r#""https://${HOST}:${PORT}""#
+        );
+        assert!(expand_err(&env, r#""BROKEN", interpolate: true"#).contains("MISSING"));
+        assert!(expand_err(&env, r#""A", interpolate: true"#)
+            .starts_with("Cyclic reference in environment variable A: A -> B -> A"));
+    }
+
+    #[test]
+    fn reports_custom_message_if_not_set() {
+        let env = TestEnv::new(&[("API_KEY", "secret")]);
+        let args = r#""API_KEY", message: "Set API_KEY in your deployment secrets""#;
+        assert_eq!(expand(&env, args).unwrap(), r#""secret""#);
+        assert_expand_err(
+            &TestEnv::new(&[]),
+            args,
+            "Set API_KEY in your deployment secrets",
+        );
+    }
+
+    #[test]
+    fn expands_socket_addresses() {
+        let env = TestEnv::new(&[
+            ("BIND", "127.0.0.1:8080"),
+            ("BIG_OCTET", "256.0.0.1:80"),
+            ("NO_PORT", "127.0.0.1"),
+            ("BAD_PORT", "127.0.0.1:http"),
+            ("SHORT", "127.0.1:80"),
+        ]);
+        let expand_socketaddr = |args| expand_macro(expand_env_socketaddr_macro, &env, args);
+        assert_eq!(
+            expand_socketaddr(r#""BIND""#).unwrap(),
+            "(127, 0, 0, 1, 8080)"
+        );
+        assert!(expand_socketaddr(r#""BIG_OCTET""#)
+            .unwrap_err()
+            .ends_with("(octet 256 is not a number between 0 and 255)"));
+        assert!(expand_socketaddr(r#""NO_PORT""#)
+            .unwrap_err()
+            .ends_with("(missing port)"));
+        assert!(expand_socketaddr(r#""BAD_PORT""#)
+            .unwrap_err()
+            .ends_with("(port http is not a number between 0 and 65535)"));
+        assert!(expand_socketaddr(r#""SHORT""#)
+            .unwrap_err()
+            .ends_with("(expected 4 octets, got 3)"));
+    }
+
+    #[test]
+    fn matches_allowed_values_ignoring_case() {
+        let env = TestEnv::new(&[("ENV_STAGE", "Prod")]);
+        assert_eq!(
+            expand(
+                &env,
+                r#""ENV_STAGE", one_of: ["dev", "prod"], case_insensitive: true"#
+            )
+            .unwrap(),
+            r#""prod""#
+        );
+        assert_expand_err(
+            &env,
+            r#""ENV_STAGE", one_of: ["dev", "prod"]"#,
+            "ENV_STAGE=Prod is not one of the allowed values: dev, prod",
+        );
+    }
+
+    #[test]
+    fn warns_when_default_is_used() {
+        let env = TestEnv::new(&[("SET", "1")]);
+        let (token_stream, warnings) =
+            expand_env_macro(r#"("PORT", 8080, warn_on_default: true)"#, &env).unwrap();
+        assert_eq!(token_stream.to_string(), "8080");
+        assert_eq!(
+            messages(warnings),
+            ["Using default for PORT; variable not set"]
+        );
+        assert!(matches!(
+            expand_env_macro(r#"("SET", 8080, warn_on_default: true)"#, &env),
+            Ok((_, warnings)) if warnings.is_empty()
+        ));
+        assert!(matches!(
+            expand_env_macro(r#"("PORT", 8080)"#, &env),
+            Ok((_, warnings)) if warnings.is_empty()
+        ));
+    }
+
+    #[test]
+    fn checks_signed_bounds() {
+        let env = TestEnv::new(&[
+            ("MIN_I8", "-128"),
+            ("MAX_I8", "127"),
+            ("UNDER_I8", "-129"),
+            ("OVER_I8", "128"),
+            ("MIN_I64", "-9223372036854775808"),
+            ("OVER_I64", "9223372036854775808"),
+        ]);
+        assert_eq!(expand(&env, r#""MIN_I8", signed: i8"#).unwrap(), "-128");
+        assert_eq!(expand(&env, r#""MAX_I8", signed: i8"#).unwrap(), "127");
+        assert!(expand_err(&env, r#""UNDER_I8", signed: i8"#).starts_with("UNDER_I8=-129"));
+        assert!(expand_err(&env, r#""OVER_I8", signed: i8"#).starts_with("OVER_I8=128"));
+        assert_eq!(
+            expand(&env, r#""MIN_I64", signed: i64"#).unwrap(),
+            "-9223372036854775808"
+        );
+        assert!(expand_err(&env, r#""OVER_I64", signed: i64"#).starts_with("OVER_I64="));
+    }
+
+    #[test]
+    fn checks_unsigned_bounds() {
+        let env = TestEnv::new(&[
+            ("MAX_U16", "65535"),
+            ("OVER_U16", "65536"),
+            ("NEGATIVE", "-1"),
+            ("MAX_U8", "255"),
+            ("OVER_U8", "256"),
+        ]);
+        assert_eq!(
+            expand(&env, r#""MAX_U16", unsigned: u16"#).unwrap(),
+            "65535"
+        );
+        assert!(expand_err(&env, r#""OVER_U16", unsigned: u16"#).starts_with("OVER_U16=65536"));
+        assert!(expand_err(&env, r#""NEGATIVE", unsigned: u16"#).starts_with("NEGATIVE=-1"));
+        assert_eq!(expand(&env, r#""MAX_U8", unsigned: u8"#).unwrap(), "255");
+        assert!(expand_err(&env, r#""OVER_U8", unsigned: u8"#).starts_with("OVER_U8=256"));
+    }
+
+    #[test]
+    fn expands_values_as_tuple() {
+        let env = TestEnv::new(&[("WORKERS", "4"), ("PORT", "8080")]);
+        let expand_all = |args| expand_macro(expand_env_all_macro, &env, args);
+        assert_eq!(expand_all(r#""WORKERS", "PORT""#).unwrap(), "(4, 8080)");
+        assert_eq!(expand_all(r#""WORKERS""#).unwrap(), "(4,)");
+        assert_eq!(
+            expand_all(r#""WORKERS", ("TIMEOUT", 30)"#).unwrap(),
+            "(4, 30)"
+        );
+        assert!(expand_all(r#""WORKERS", "TIMEOUT""#)
+            .unwrap_err()
+            .starts_with("Environment variable TIMEOUT not set"));
+        assert_eq!(
+            expand_all(r#""TIMEOUT", "RETRIES""#).unwrap_err(),
+            "Environment variables TIMEOUT, RETRIES not set"
+        );
+    }
+
+    #[test]
+    fn resolves_manifest_after_dotenv() {
+        let mut env = TestEnv::new(&[("SET", "1")]).with_dotenv(&[("DOTENV", "2")]);
+        env.manifest = HashMap::from([
+            ("SET".to_string(), "10".to_string()),
+            ("DOTENV".to_string(), "20".to_string()),
+            ("MANIFEST".to_string(), "30".to_string()),
+        ]);
+        assert_eq!(expand(&env, r#""SET", toml: true"#).unwrap(), "1");
+        assert_eq!(expand(&env, r#""DOTENV", toml: true"#).unwrap(), "2");
+        assert_eq!(expand(&env, r#""MANIFEST", toml: true"#).unwrap(), "30");
+        assert_eq!(expand(&env, r#""MANIFEST", 0"#).unwrap(), "0");
+    }
+
+    #[test]
+    fn joins_values_with_separator() {
+        let env = TestEnv::new(&[("NAME", "foo"), ("VERSION", "1.2.3"), ("BUILD", "7")]);
+        assert_eq!(
+            expand(&env, r#""NAME", "VERSION", join: "-""#).unwrap(),
+            r#""foo-1.2.3""#
+        );
+        assert_eq!(
+            expand(&env, r#""NAME", "VERSION", "BUILD", join: "+""#).unwrap(),
+            r#""foo+1.2.3+7""#
+        );
+        assert!(expand_err(&env, r#""NAME", "MISSING", join: "-""#).contains("MISSING"));
+    }
+
+    #[test]
+    fn folds_arithmetic_defaults() {
+        let env = TestEnv::new(&[]);
+        assert_eq!(expand(&env, r#""SLOT", 60 * 60"#).unwrap(), "3600");
+        assert_eq!(expand(&env, r#""SLOT", (1 + 2) * 3"#).unwrap(), "9");
+        assert_eq!(expand(&env, r#""SLOT", 7 / 2 - 1"#).unwrap(), "2");
+        assert!(expand_err(&env, r#""SLOT", 1 / 0"#).starts_with("Division by zero"));
+    }
+
+    #[test]
+    fn resolves_global_prefix_first() {
+        let unprefixed = TestEnv::new(&[("PORT", "8080")]);
+        assert_eq!(expand(&unprefixed, r#""PORT""#).unwrap(), "8080");
+        let prefixed = TestEnv::new(&[
+            ("CAIRO_ENV_PREFIX", "APP_"),
+            ("APP_PORT", "9090"),
+            ("PORT", "8080"),
+            ("HOST", "1"),
+        ]);
+        assert_eq!(expand(&prefixed, r#""PORT""#).unwrap(), "9090");
+        assert_eq!(expand(&prefixed, r#""HOST""#).unwrap(), "1");
+    }
+
+    #[test]
+    fn expands_hex_literals() {
+        let env = TestEnv::new(&[("SALT", "255"), ("HEX", "0xABC"), ("NEGATIVE", "-1")]);
+        assert_eq!(expand(&env, r#""SALT", kind: hex"#).unwrap(), "0xff");
+        assert_eq!(expand(&env, r#""HEX", kind: hex"#).unwrap(), "0xabc");
+        assert!(expand_err(&env, r#""NEGATIVE", kind: hex"#).contains("negative"));
+    }
+
+    #[test]
+    fn rejects_duplicate_arguments() {
+        let env = TestEnv::new(&[("PORT", "1")]);
+        assert_expand_err(
+            &env,
+            r#""PORT", default: 1, default: 2"#,
+            "Duplicate argument: default",
+        );
+        assert_expand_err(
+            &env,
+            r#""PORT", 1, default: 2"#,
+            "Duplicate argument: default",
+        );
+        assert_expand_err(&env, r#""PORT", min: 1, min: 2"#, "Duplicate argument: min");
+    }
+
+    #[test]
+    fn transforms_case_of_strings() {
+        let env = TestEnv::new(&[("CHAIN", "Sepolia"), ("PORT", "8080")]);
+        assert_eq!(
+            expand(&env, r#""CHAIN", transform: lowercase"#).unwrap(),
+            r#""sepolia""#
+        );
+        assert_eq!(
+            expand(&env, r#""CHAIN", transform: uppercase"#).unwrap(),
+            r#""SEPOLIA""#
+        );
+        assert!(expand_err(&env, r#""PORT", transform: lowercase"#).starts_with("PORT=8080"));
+        assert!(
+            expand_err(&env, r#""PORT", transform: lowercase, kind: felt"#).contains("transform:")
+        );
+    }
+
+    #[test]
+    fn packs_uuids() {
+        let env = TestEnv::new(&[
+            ("TRACE_ID", "00000000-0000-0000-0000-000000000102"),
+            ("MALFORMED", "00000000-0000-0000-0000-00000000010"),
+        ]);
+        let expand_uuid = |args| expand_macro(expand_env_uuid_macro, &env, args);
+        assert_eq!(expand_uuid(r#""TRACE_ID""#).unwrap(), "258");
+        assert_eq!(
+            expand_uuid(r#""TRACE_ID", endian: little"#).unwrap(),
+            (BigInt::from(0x0201) << 112_u32).to_string()
+        );
+        assert!(expand_uuid(r#""MALFORMED""#)
+            .unwrap_err()
+            .contains("00000000-0000-0000-0000-00000000010"));
+    }
+
+    #[test]
+    fn resolves_fallback_variable() {
+        let both = TestEnv::new(&[("PRIMARY", "1"), ("SECONDARY", "2")]);
+        assert_eq!(
+            expand(&both, r#""PRIMARY", fallback_var: "SECONDARY""#).unwrap(),
+            "1"
+        );
+        let secondary = TestEnv::new(&[("SECONDARY", "2")]);
+        assert_eq!(
+            expand(&secondary, r#""PRIMARY", fallback_var: "SECONDARY""#).unwrap(),
+            "2"
+        );
+        let none = TestEnv::new(&[]);
+        assert_eq!(
+            expand(&none, r#""PRIMARY", fallback_var: "SECONDARY", default: 3"#).unwrap(),
+            "3"
+        );
+        let err = expand_err(&none, r#""PRIMARY", fallback_var: "SECONDARY""#);
+        assert!(
+            err.contains("PRIMARY") && err.contains("SECONDARY"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn suggests_default_for_unset_variable() {
+        let env = TestEnv::new(&[]);
+        assert!(expand_err(&env, r#""FOO""#)
+            .contains("help: set FOO or pass a default like env!(\"FOO\", 0)"));
+        assert!(expand_macro(expand_env_duration_macro, &env, r#""TTL""#)
+            .unwrap_err()
+            .contains("help: set TTL or pass a default like env_duration!(\"TTL\", 0)"));
+    }
+
+    #[test]
+    fn rejects_zero_with_nonzero() {
+        let env = TestEnv::new(&[("ZERO", "0"), ("DECIMALS", "18")]);
+        assert_expand_err(&env, r#""ZERO", nonzero: true"#, "ZERO=0 must be nonzero");
+        assert_eq!(expand(&env, r#""DECIMALS", nonzero: true"#).unwrap(), "18");
+        assert_expand_err(
+            &env,
+            r#""UNSET", 0, nonzero: true"#,
+            "UNSET=0 must be nonzero",
+        );
+        assert_eq!(expand(&env, r#""UNSET", 6, nonzero: true"#).unwrap(), "6");
+        assert_eq!(expand(&env, r#""ZERO""#).unwrap(), "0");
+    }
+
+    #[test]
+    fn packs_colors() {
+        let env = TestEnv::new(&[
+            ("RGB", "#1a2b3c"),
+            ("RGBA", "#1a2b3cff"),
+            ("SHORT", "#123"),
+            ("NOT_HEX", "#1a2b3g"),
+        ]);
+        let expand_color = |args| expand_macro(expand_env_color_macro, &env, args);
+        assert_eq!(expand_color(r#""RGB""#).unwrap(), 0x1a2b3c.to_string());
+        assert_eq!(
+            expand_color(r#""RGBA""#).unwrap(),
+            0x1a2b3cff_u32.to_string()
+        );
+        assert_eq!(
+            expand_color(r##""UNSET", "#ffffff""##).unwrap(),
+            0xffffff.to_string()
+        );
+        assert_eq!(
+            expand_color(r##""UNSET", "#ffffff", "#000000""##).unwrap_err(),
+            "Unexpected argument\nnote: in `\"#000000\"`"
+        );
+        assert!(expand_color(r#""SHORT""#).unwrap_err().contains("#123"));
+        assert!(expand_color(r#""NOT_HEX""#)
+            .unwrap_err()
+            .contains("#1a2b3g"));
+    }
+
+    #[test]
+    fn expands_semantic_versions() {
+        let env = TestEnv::new(&[
+            ("FULL", "1.2.3"),
+            ("PARTIAL", "1.2"),
+            ("SUFFIXED", "1.2.3-rc.1+build.5"),
+            ("INVALID", "1.x.3"),
+        ]);
+        let expand_semver = |args| expand_macro(expand_env_semver_macro, &env, args);
+        assert_eq!(expand_semver(r#""FULL""#).unwrap(), "(1, 2, 3)");
+        assert_eq!(expand_semver(r#""PARTIAL""#).unwrap(), "(1, 2, 0)");
+        assert_eq!(expand_semver(r#""SUFFIXED""#).unwrap(), "(1, 2, 3)");
+        assert!(expand_semver(r#""INVALID""#).unwrap_err().contains("1.x.3"));
+    }
+
+    #[test]
+    fn names_the_call_in_syntax_errors() {
+        let env = TestEnv::new(&[]);
+        let err = messages(expand_env_macro(r#"("PORT" 1)"#, &env).unwrap_err()).remove(0);
+        assert!(
+            err.starts_with(r#"Could not parse env!("PORT") arguments"#),
+            "{}",
+            err
+        );
+        let err = messages(expand_env_macro(r#"("A\"B" 1)"#, &env).unwrap_err()).remove(0);
+        assert!(
+            err.starts_with(r#"Could not parse env!("A\"B") arguments"#),
+            "{}",
+            err
+        );
+        let err = expand_macro(expand_env_bool_macro, &env, "1 2").unwrap_err();
+        assert!(
+            err.starts_with("Could not parse env_bool! arguments"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn expands_empty_values_with_allow_empty() {
+        let env = TestEnv::new(&[("LABEL", ""), ("PORT", "  ")]);
+        assert_eq!(
+            expand(&env, r#""LABEL", default: "none""#).unwrap(),
+            r#""none""#
+        );
+        assert_eq!(
+            expand(&env, r#""LABEL", allow_empty: true"#).unwrap(),
+            r#""""#
+        );
+        assert_eq!(
+            expand(&env, r#""LABEL", allow_empty: true, kind: bytearray"#).unwrap(),
+            r#""""#
+        );
+        assert_expand_err(
+            &env,
+            r#""LABEL", allow_empty: true, kind: felt"#,
+            "LABEL is not numeric (kind: felt requested)",
+        );
+        assert_expand_err(
+            &env,
+            r#""PORT", allow_empty: true, kind: hex"#,
+            "PORT is not numeric (kind: hex requested)",
+        );
+        assert_expand_err(
+            &env,
+            r#""LABEL", allow_empty: true, kind: bool"#,
+            "Failed to parse boolean value of environment variable LABEL",
+        );
+    }
+
+    #[test]
+    fn rejects_both_signed_and_unsigned() {
+        let env = TestEnv::new(&[("OFFSET", "-5")]);
+        assert_eq!(expand(&env, r#""OFFSET", signed: i8"#).unwrap(), "-5");
+        assert_expand_err(
+            &env,
+            r#""OFFSET", signed: i8, unsigned: u8"#,
+            "signed: and unsigned: can't be combined",
+        );
+        assert_expand_err(
+            &env,
+            r#""OFFSET", unsigned: u8, signed: i8"#,
+            "signed: and unsigned: can't be combined",
+        );
+    }
+
+    #[test]
+    fn joins_values_applying_string_options() {
+        let env = TestEnv::new(&[("NAME", " 'App' "), ("VERSION", "V2")]);
+        assert_eq!(
+            expand(&env, r#""NAME", "VERSION", join: "-", trim: true, strip_quotes: true, transform: lowercase"#)
+                .unwrap(),
+            r#""app-v2""#
+        );
+        assert_expand_err(
+            &env,
+            r#""NAME", "VERSION", join: "-", trim: true, one_of: ["'App'"]"#,
+            "VERSION=V2 is not one of the allowed values: 'App'",
+        );
+        assert_expand_err(
+            &env,
+            r#""NAME", "VERSION", join: "-", matches: "^[a-z]+$""#,
+            "NAME= 'App'  does not match the pattern",
+        );
+        assert_expand_err(
+            &env,
+            r#""NAME", "VERSION", join: "-", max_len: 4"#,
+            "NAME, VERSION is 10 bytes long, longer than max_len 4",
+        );
+    }
+
+    #[test]
+    fn joins_default_as_string() {
+        let env = TestEnv::new(&[("NAME", "app")]);
+        assert_eq!(
+            expand(&env, r#""NAME", "VERSION", join: "-", default: 1"#).unwrap(),
+            r#""1""#
+        );
+        assert_expand_err(
+            &env,
+            r#""NAME", "VERSION", join: "-", default: 1, strict_default: true"#,
+            "Default 1 of NAME does not match kind: bytearray",
+        );
+    }
+
+    #[test]
+    fn rejects_options_conflicting_with_join() {
+        let env = TestEnv::new(&[("NAME", "app"), ("VERSION", "2")]);
+        for option in [
+            "kind: felt",
+            "fallback_var: \"X\"",
+            "strip_prefix: \"APP_\"",
+            "min: 1",
+        ] {
+            let args = format!(r#""NAME", "VERSION", join: "-", {}"#, option);
+            assert_expand_err(&env, &args, "join: can't be combined with");
+        }
+        assert_eq!(
+            expand(&env, r#""NAME", "VERSION", join: "-", kind: bytearray"#).unwrap(),
+            r#""app-2""#
+        );
+    }
+
+    #[test]
+    fn reads_value_files_relative_to_package() {
+        let package_dir = test_dir("from_file");
+        std::fs::write(package_dir.join("api_key"), "\u{feff}secret\r\n").unwrap();
+        let mut env = TestEnv::new(&[("API_KEY_FILE", "api_key"), ("MISSING_FILE", "missing")]);
+        env.package_dir = Some(package_dir.clone());
+        assert_eq!(
+            expand(&env, r#""API_KEY_FILE", from_file: true"#).unwrap(),
+            r#""secret""#
+        );
+        assert_expand_err(
+            &env,
+            r#""MISSING_FILE", from_file: true"#,
+            &format!(
+                "Failed to read file {} named by environment variable MISSING_FILE",
+                package_dir.join("missing").display()
+            ),
+        );
+    }
+
+    #[test]
+    fn expands_scientific_notation() {
+        let env = TestEnv::new(&[
+            ("SUPPLY", "1e6"),
+            ("LIMIT", "2E3"),
+            ("FEE", "1.5e1"),
+            ("FRACTION", "15e-1"),
+            ("TENTH", "1e-1"),
+            ("INEXACT", "1.25e1"),
+        ]);
+        assert_eq!(expand(&env, r#""SUPPLY""#).unwrap(), "1000000");
+        assert_eq!(expand(&env, r#""LIMIT""#).unwrap(), "2000");
+        assert_eq!(expand(&env, r#""FEE""#).unwrap(), "15");
+        assert_eq!(expand(&env, r#""SUPPLY", kind: hex"#).unwrap(), "0xf4240");
+        assert_expand_err(
+            &env,
+            r#""FRACTION""#,
+            "Failed to parse numeric value of environment variable FRACTION: 15e-1 (negative exponents are not supported)",
+        );
+        assert_expand_err(
+            &env,
+            r#""TENTH""#,
+            "Failed to parse numeric value of environment variable TENTH: 1e-1 (negative exponents are not supported)",
+        );
+        assert_expand_err(
+            &env,
+            r#""INEXACT""#,
+            "Failed to parse numeric value of environment variable INEXACT: 1.25e1 (not an integer)",
+        );
+        assert_eq!(
+            expand(&env, r#""UNSET", default: "2e3", kind: felt"#).unwrap(),
+            "2000"
+        );
+        assert_eq!(
+            expand(&env, r#""UNSET", default: "1e6", kind: hex"#).unwrap(),
+            "0xf4240"
+        );
+        assert!(expand_err(&env, r#""UNSET", default: "15e-1", kind: felt"#)
+            .contains("negative exponents are not supported"));
+    }
+
+    #[test]
+    fn asserts_variable_value() {
+        let env = TestEnv::new(&[("NETWORK", "mainnet")]);
+        let expand_assert_eq = |args| expand_macro(expand_env_assert_eq_macro, &env, args);
+        assert_eq!(expand_assert_eq(r#""NETWORK", "mainnet""#).unwrap(), "()");
+        let err = expand_assert_eq(r#""NETWORK", "sepolia""#).unwrap_err();
+        assert!(
+            err.contains("mainnet") && err.contains("sepolia"),
+            "{}",
+            err
+        );
+        assert!(expand_assert_eq(r#""UNSET", "mainnet""#)
+            .unwrap_err()
+            .starts_with("Environment variable UNSET not set"));
+    }
+
+    #[test]
+    fn expands_json_scalars() {
+        let env = TestEnv::new(&[(
+            "CONFIG_JSON",
+            r#"{"workers": 4, "name": "app", "limits": {"max": 10}}"#,
+        )]);
+        let expand_json = |args| expand_macro(expand_env_json_macro, &env, args);
+        assert_eq!(
+            expand_json(r#""CONFIG_JSON", path: "workers""#).unwrap(),
+            "4"
+        );
+        assert_eq!(
+            expand_json(r#""CONFIG_JSON", path: "limits.max""#).unwrap(),
+            "10"
+        );
+        assert!(expand_json(r#""CONFIG_JSON", path: "missing""#)
+            .unwrap_err()
+            .contains("missing"));
+        assert!(expand_json(r#""CONFIG_JSON", path: "name""#)
+            .unwrap_err()
+            .contains("name"));
+    }
+
+    #[test]
+    fn reports_source_of_invalid_value() {
+        let env = TestEnv::new(&[("SHELL_PORT", "abc")])
+            .with_dotenv(&[("FILE_PORT", "abc"), ("SHELL_PORT", "1")]);
+        assert!(expand_err(&env, r#""SHELL_PORT", kind: felt"#)
+            .ends_with("note: SHELL_PORT was read from the environment"));
+        assert!(expand_err(&env, r#""FILE_PORT", kind: felt"#)
+            .ends_with("note: FILE_PORT was read from .env"));
+        let mut env = TestEnv::new(&[]);
+        env.manifest = HashMap::from([("TOML_PORT".to_string(), "abc".to_string())]);
+        assert!(expand_err(&env, r#""TOML_PORT", kind: felt, toml: true"#)
+            .ends_with("note: TOML_PORT was read from Scarb.toml"));
+    }
+
+    #[test]
+    fn scales_fixed_point_decimals() {
+        let env = TestEnv::new(&[("PRICE", "12.34"), ("OFFSET", "-0.5"), ("PRECISE", "1.234")]);
+        let expand_fixed = |args| expand_macro(expand_env_f64_fixed_macro, &env, args);
+        assert_eq!(expand_fixed(r#""PRICE", scale: 2"#).unwrap(), "1234");
+        assert_eq!(expand_fixed(r#""PRICE""#).unwrap(), "12340000000000000000");
+        assert_eq!(expand_fixed(r#""OFFSET", scale: 1"#).unwrap(), "-5");
+        assert!(expand_fixed(r#""PRECISE", scale: 2"#)
+            .unwrap_err()
+            .contains("1.234"));
+    }
+
+    #[test]
+    fn expands_strings_only_if_toggled() {
+        let off = TestEnv::new(&[("VERSION", "42")]);
+        assert_eq!(expand(&off, r#""VERSION""#).unwrap(), "42");
+        let on = TestEnv::new(&[("VERSION", "42"), ("CAIRO_ENV_STRING_ONLY", "1")]);
+        assert_eq!(expand(&on, r#""VERSION""#).unwrap(), r#""42""#);
+        assert_eq!(expand(&on, r#""VERSION", kind: felt"#).unwrap(), "42");
+        let invalid = TestEnv::new(&[("VERSION", "42"), ("CAIRO_ENV_STRING_ONLY", "maybe")]);
+        assert!(expand_err(&invalid, r#""VERSION""#).contains("CAIRO_ENV_STRING_ONLY"));
+    }
+
+    #[test]
+    fn explains_numeric_errors() {
+        let env = TestEnv::new(&[
+            ("HEX", "0x80zz"),
+            ("BIN", "0b12"),
+            ("SEPARATED", "1__0"),
+            ("EMPTY", "0x"),
+        ]);
+        assert!(expand_err(&env, r#""HEX""#).contains("(invalid digit `z` at position 5)"));
+        assert!(expand_err(&env, r#""BIN""#).contains("(invalid digit `2` at position 4)"));
+        assert!(expand_err(&env, r#""SEPARATED""#)
+            .contains("(misplaced digit separator at position 2)"));
+        assert!(expand_err(&env, r#""EMPTY""#).contains("(no digits)"));
+    }
+
+    #[test]
+    fn combines_flags() {
+        let env = TestEnv::new(&[("FLAG_A", "1"), ("FLAG_B", "0b100"), ("BAD", "on")]);
+        let expand_flags = |args| expand_macro(expand_env_flags_macro, &env, args);
+        assert_eq!(
+            expand_flags(r#""FLAG_A", "FLAG_B", "FLAG_C", default: 0"#).unwrap(),
+            "5"
+        );
+        assert!(expand_flags(r#""FLAG_A", "BAD", default: 0"#)
+            .unwrap_err()
+            .contains("BAD"));
+    }
+
+    #[test]
+    fn formats_resolution_log_line() {
+        let token_stream = TokenStream::new("8080".to_string());
+        assert_eq!(
+            resolution_log_line("env!", "PORT", ".env", &token_stream),
+            "env!: PORT = 8080 (from .env)"
+        );
+        let env = TestEnv::new(&[("PORT", "8080")]);
+        assert_eq!(expand(&env, r#""PORT", debug: true"#).unwrap(), "8080");
+        assert_eq!(expand(&env, r#""UNSET", 1, debug: true"#).unwrap(), "1");
+    }
+
+    #[test]
+    fn tolerates_trailing_commas() {
+        let env = TestEnv::new(&[("X", "5")]);
+        assert_eq!(
+            expand(&env, r#""X","#).unwrap(),
+            expand(&env, r#""X""#).unwrap()
+        );
+        assert_eq!(
+            expand(&env, r#""X", 1,"#).unwrap(),
+            expand(&env, r#""X", 1"#).unwrap()
+        );
+        assert_eq!(expand(&env, r#""UNSET", 1,"#).unwrap(), "1");
+    }
+
+    #[test]
+    fn expands_character_arrays() {
+        let env = TestEnv::new(&[("WORD", "abc"), ("QUOTE", "it's"), ("ACCENT", "café")]);
+        let expand_chars = |args| expand_macro(expand_env_chars_macro, &env, args);
+        assert_eq!(expand_chars(r#""WORD""#).unwrap(), "array!['a', 'b', 'c']");
+        assert_eq!(
+            expand_chars(r#""QUOTE""#).unwrap(),
+            r"array!['i', 't', '\'', 's']"
+        );
+        assert!(expand_chars(r#""ACCENT""#).unwrap_err().contains("ACCENT"));
+    }
+
+    #[test]
+    fn checks_strings_against_pattern() {
+        let env = TestEnv::new(&[("SLUG", "my-app-2"), ("BAD_SLUG", "My App")]);
+        assert_eq!(
+            expand(&env, r#""SLUG", matches: "^[a-z0-9-]+$""#).unwrap(),
+            r#""my-app-2""#
+        );
+        assert!(expand_err(&env, r#""BAD_SLUG", matches: "^[a-z0-9-]+$""#)
+            .starts_with("BAD_SLUG=My App does not match the pattern ^[a-z0-9-]+$"));
+        assert!(expand_err(&env, r#""SLUG", matches: "[a-z""#).contains("[a-z"));
+    }
+
+    #[test]
+    fn expands_dictionary_initializers() {
+        let env = TestEnv::new(&[
+            ("LIMITS", "a:1, b:2"),
+            ("EMPTY", ""),
+            ("NO_COLON", "a:1,b"),
+            ("NOT_NUMERIC", "a:x"),
+        ]);
+        let expand_map = |args| expand_macro(expand_env_map_macro, &env, args);
+        let dict = "let mut dict: core::dict::Felt252Dict<felt252> = Default::default();";
+        assert_eq!(
+            expand_map(r#""LIMITS""#).unwrap(),
+            format!(
+                "{{ {} dict.insert('a', 1); dict.insert('b', 2); dict }}",
+                dict
+            )
+        );
+        assert_eq!(
+            expand_map(r#""EMPTY""#).unwrap(),
+            format!("{{ {} dict }}", dict)
+        );
+        assert_eq!(
+            expand_map(r#""NO_COLON""#).unwrap_err(),
+            "Invalid pair `b` in environment variable NO_COLON: expected key:value"
+        );
+        assert_eq!(
+            expand_map(r#""NOT_NUMERIC""#).unwrap_err(),
+            "Invalid pair `a:x` in environment variable NOT_NUMERIC: the value is not a number"
+        );
+    }
+
+    #[test]
+    fn checks_range_of_custom_prime() {
+        let env = TestEnv::new(&[("SMALL", "5"), ("LARGE", "20")]);
+        assert_eq!(expand(&env, r#""SMALL", prime: 17"#).unwrap(), "5");
+        assert!(expand_err(&env, r#""LARGE", prime: 17"#)
+            .starts_with("LARGE=20 does not fit into the field of prime 17"));
+        assert_eq!(
+            expand(&env, r#""LARGE", prime: 17, wrapping: true"#).unwrap(),
+            "3"
+        );
+        assert_expand_err(&env, r#""SMALL", prime: 1"#, "prime must be at least 2");
+    }
+
+    #[test]
+    fn rejects_values_not_satisfying_kind() {
+        let env = TestEnv::new(&[("PORT", "http"), ("FLAG", "maybe")]);
+        assert_expand_err(
+            &env,
+            r#""PORT", kind: felt"#,
+            "PORT is not numeric (kind: felt requested): http",
+        );
+        assert_expand_err(
+            &env,
+            r#""PORT", kind: hex"#,
+            "PORT is not numeric (kind: hex requested): http",
+        );
+        assert!(expand_err(&env, r#""FLAG", kind: bool"#).contains("maybe"));
+    }
+
+    #[test]
+    fn checks_paths_exist() {
+        let dir = test_dir("env_path");
+        let missing = dir.join("missing");
+        let env = TestEnv::new(&[
+            ("ASSET_DIR", dir.to_str().unwrap()),
+            ("MISSING_DIR", missing.to_str().unwrap()),
+        ]);
+        let expand_path = |args| expand_macro(expand_env_path_macro, &env, args);
+        assert_eq!(
+            expand_path(r#""ASSET_DIR""#).unwrap(),
+            format!("\"{}\"", dir.display())
+        );
+        assert!(expand_path(r#""MISSING_DIR""#)
+            .unwrap_err()
+            .contains(missing.to_str().unwrap()));
+        assert_eq!(
+            expand_path(r#""MISSING_DIR", must_exist: false"#).unwrap(),
+            format!("\"{}\"", missing.display())
+        );
+    }
+
+    #[test]
+    fn keeps_escaped_dollars() {
+        let env = TestEnv::new(&[
+            ("PRICE", r"\$5"),
+            ("DOUBLED", "$$5"),
+            ("AMOUNT", "5"),
+            ("TOTAL", r"\$${AMOUNT}"),
+        ]);
+        assert_eq!(
+            expand(&env, r#""PRICE", interpolate: true"#).unwrap(),
+            r#""$5""#
+        );
+        assert_eq!(
+            expand(&env, r#""DOUBLED", interpolate: true"#).unwrap(),
+            r#""$5""#
+        );
+        assert_eq!(
+            expand(&env, r#""TOTAL", interpolate: true"#).unwrap(),
+            r#""$5""#
+        );
+        assert_eq!(expand(&env, r#""PRICE""#).unwrap(), r#""\\$5""#);
+        assert_eq!(expand(&env, r#""DOUBLED""#).unwrap(), r#""$$5""#);
+    }
+
+    #[test]
+    fn packs_bytes_in_requested_order() {
+        let env = TestEnv::new(&[
+            ("SYMBOL", "AB"),
+            ("TRACE_ID", "00010203-0405-0607-0809-0a0b0c0d0e0f"),
+        ]);
+        let expand_shortstring = |args| expand_macro(expand_env_shortstring_macro, &env, args);
+        assert_eq!(
+            expand_shortstring(r#""SYMBOL""#).unwrap(),
+            0x4142.to_string()
+        );
+        assert_eq!(
+            expand_shortstring(r#""SYMBOL", endian: big"#).unwrap(),
+            0x4142.to_string()
+        );
+        assert_eq!(
+            expand_shortstring(r#""SYMBOL", endian: little"#).unwrap(),
+            0x4241.to_string()
+        );
+        let expand_uuid = |args| expand_macro(expand_env_uuid_macro, &env, args);
+        assert_eq!(
+            expand_uuid(r#""TRACE_ID""#).unwrap(),
+            0x000102030405060708090a0b0c0d0e0f_u128.to_string()
+        );
+        assert_eq!(
+            expand_uuid(r#""TRACE_ID", endian: little"#).unwrap(),
+            0x0f0e0d0c0b0a09080706050403020100_u128.to_string()
+        );
+    }
+
+    #[test]
+    fn checks_string_length() {
+        let env = TestEnv::new(&[("LABEL", "héllo")]);
+        assert_eq!(
+            expand(&env, r#""LABEL", min_len: 6, max_len: 6"#).unwrap(),
+            r#""h\xc3\xa9llo""#
+        );
+        assert!(expand_err(&env, r#""LABEL", max_len: 5"#)
+            .contains("6 bytes long, longer than max_len 5"));
+        assert!(expand_err(&env, r#""LABEL", min_len: 7"#)
+            .contains("6 bytes long, shorter than min_len 7"));
+    }
+
+    #[test]
+    fn coerces_booleans_to_felt() {
+        let env = TestEnv::new(&[("ENABLED", "true"), ("VERBOSE", "off"), ("MODE", "fast")]);
+        assert_eq!(expand(&env, r#""ENABLED", kind: felt"#).unwrap(), "1");
+        assert_eq!(expand(&env, r#""VERBOSE", kind: felt"#).unwrap(), "0");
+        assert_eq!(expand(&env, r#""ENABLED""#).unwrap(), r#""true""#);
+        assert_expand_err(
+            &env,
+            r#""MODE", kind: felt"#,
+            "MODE is not numeric (kind: felt requested): fast",
+        );
+    }
+
+    #[test]
+    fn prefers_requested_source() {
+        let env = TestEnv::new(&[("PORT", "8080"), ("HOST", "1")]).with_dotenv(&[("PORT", "9090")]);
+        assert_eq!(expand(&env, r#""PORT""#).unwrap(), "8080");
+        assert_eq!(expand(&env, r#""PORT", prefer: env"#).unwrap(), "8080");
+        assert_eq!(expand(&env, r#""PORT", prefer: dotenv"#).unwrap(), "9090");
+        assert_eq!(expand(&env, r#""HOST", prefer: dotenv"#).unwrap(), "1");
+        assert_expand_err(
+            &env,
+            r#""PORT", prefer: toml"#,
+            "Unsupported prefer: toml, expected one of env, dotenv",
+        );
+    }
+
+    #[test]
+    fn expands_enum_discriminants() {
+        let expand_enum = |state: &str, args: &str| {
+            expand_macro(
+                expand_env_enum_macro,
+                &TestEnv::new(&[("STATE", state)]),
+                args,
+            )
+        };
+        let states = r#""STATE", ["idle", "active", "done"]"#;
+        assert_eq!(expand_enum("idle", states).unwrap(), "0");
+        assert_eq!(expand_enum("active", states).unwrap(), "1");
+        assert_eq!(expand_enum("done", states).unwrap(), "2");
+        assert_eq!(
+            expand_enum("done", &format!("{}, start: 1", states)).unwrap(),
+            "3"
+        );
+        let err = expand_enum("paused", states).unwrap_err();
+        assert!(
+            err.contains("paused") && err.contains("idle, active, done"),
+            "{}",
+            err
+        );
+    }
+
+    /// Expands `#[env_consts]` with the given attribute arguments on the given item,
+    /// returning the expanded item and the warning messages, or the error message.
+    fn expand_consts(
+        env: &TestEnv,
+        attr: &str,
+        item: &str,
+    ) -> Result<(String, Vec<String>), String> {
+        expand_env_consts_macro(attr, item, env)
+            .map(|(token_stream, warnings)| (token_stream.to_string(), messages(warnings)))
+            .map_err(|err| err.message)
+    }
+
+    #[test]
+    fn declares_constants_for_prefixed_variables() {
+        let env = TestEnv::new(&[
+            ("MYAPP_PORT", "8080"),
+            ("MYAPP_HOST", "localhost"),
+            ("MYAPP_max-size", "10"),
+            ("MYAPP_TAG", "0xZZ"),
+            ("MYAPP_ID", "1_"),
+            ("MYAPP_HUGE", PRIME),
+            ("OTHER_PORT", "1"),
+        ])
+        .with_dotenv(&[("MYAPP_PORT", "9090"), ("MYAPP_WORKERS", "0x10")]);
+        let (expansion, warnings) = expand_consts(&env, r#"("MYAPP_")"#, "mod config {}").unwrap();
+        assert_eq!(
+            expansion,
+            "mod config {\n    const PORT: felt252 = 8080;\n    const WORKERS: felt252 = 16;\n}"
+        );
+        assert_eq!(
+            warnings,
+            [
+                "Skipping environment variable MYAPP_HOST: localhost is not numeric, and constants can't hold a ByteArray".to_string(),
+                format!("Skipping environment variable MYAPP_HUGE: MYAPP_HUGE={} does not fit into felt252, pass wrapping: true to reduce it modulo the field prime", PRIME),
+                "Skipping environment variable MYAPP_ID: Failed to parse numeric value of environment variable MYAPP_ID: 1_ (misplaced digit separator at position 2)".to_string(),
+                "Skipping environment variable MYAPP_TAG: Failed to parse numeric value of environment variable MYAPP_TAG: 0xZZ (invalid digit `Z` at position 3)".to_string(),
+                "Skipping environment variable MYAPP_max-size: MAX-SIZE is not a valid constant name".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_misplaced_env_consts() {
+        let mut env = TestEnv::new(&[]);
+        assert!(expand_consts(&env, r#"("MYAPP_")"#, "mod config;")
+            .unwrap_err()
+            .starts_with("#[env_consts] can only be applied to a module with a body"));
+        assert!(expand_consts(&env, r#"("")"#, "mod config {}")
+            .unwrap_err()
+            .starts_with("Variable name prefix must not be empty"));
+        env.dotenv = Err(".env: unterminated quoted value of KEY starting at line 1".to_string());
+        assert!(expand_consts(&env, r#"("MYAPP_")"#, "mod config {}")
+            .unwrap_err()
+            .starts_with("Failed to parse .env file"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn fails_on_non_unicode_values() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let mut env = TestEnv::new(&[]);
+        env.vars
+            .insert("PORT".to_string(), OsString::from_vec(vec![0xff]));
+        assert_expand_err(&env, r#""PORT", 1"#, "PORT is set but not valid UTF-8");
+        assert_expand_err(
+            &env,
+            r#""HOST", "PORT", default: 1"#,
+            "PORT is set but not valid UTF-8",
+        );
+
+        let mut env = TestEnv::new(&[("CAIRO_ENV_PREFIX", "APP_"), ("PORT", "8080")]);
+        env.vars
+            .insert("APP_PORT".to_string(), OsString::from_vec(vec![0xff]));
+        assert_expand_err(&env, r#""PORT""#, "APP_PORT is set but not valid UTF-8");
+    }
+
+    #[test]
+    fn resolves_relative_paths_against_package() {
+        let package_dir = test_dir("env_path_relative");
+        std::fs::create_dir(package_dir.join("assets")).unwrap();
+        let mut env = TestEnv::new(&[("ASSET_DIR", "assets"), ("CACHE_DIR", "cache")]);
+        env.package_dir = Some(package_dir.clone());
+        let expand_path = |args| expand_macro(expand_env_path_macro, &env, args);
+        assert_eq!(
+            expand_path(r#""ASSET_DIR""#).unwrap(),
+            format!("\"{}\"", package_dir.join("assets").display())
+        );
+        assert!(expand_path(r#""CACHE_DIR""#)
+            .unwrap_err()
+            .contains(package_dir.join("cache").to_str().unwrap()));
+        assert_eq!(
+            expand_path(r#""CACHE_DIR", must_exist: false"#).unwrap(),
+            format!("\"{}\"", package_dir.join("cache").display())
+        );
+    }
+
+    #[test]
+    fn names_first_set_variable() {
+        let names = r#"["PROD_URL", "STAGING_URL", "DEV_URL"]"#;
+        let first = TestEnv::new(&[("PROD_URL", "a"), ("DEV_URL", "c")]);
+        assert_eq!(
+            expand_macro(expand_env_which_macro, &first, names).unwrap(),
+            r#""PROD_URL""#
+        );
+        let later = TestEnv::new(&[("DEV_URL", "c")]);
+        assert_eq!(
+            expand_macro(expand_env_which_macro, &later, names).unwrap(),
+            r#""DEV_URL""#
+        );
+        let none = TestEnv::new(&[]);
+        assert_eq!(
+            expand_macro(
+                expand_env_which_macro,
+                &none,
+                &format!(r#"{}, "LOCAL""#, names)
+            )
+            .unwrap(),
+            r#""LOCAL""#
+        );
+        assert!(expand_macro(expand_env_which_macro, &none, names).is_err());
+    }
+
+    #[test]
+    fn rejects_overly_long_numbers() {
+        let env = TestEnv::new(&[
+            ("AT_LIMIT", &"9".repeat(75)),
+            ("OVER_LIMIT", &"9".repeat(101)),
+            ("SHORT", "12345"),
+        ]);
+        assert_eq!(expand(&env, r#""AT_LIMIT""#).unwrap(), "9".repeat(75));
+        assert_expand_err(
+            &env,
+            r#""OVER_LIMIT""#,
+            "OVER_LIMIT is a numeric value of 101 characters, longer than max_digits 100",
+        );
+        assert_eq!(expand(&env, r#""SHORT", max_digits: 5"#).unwrap(), "12345");
+        assert_expand_err(
+            &env,
+            r#""SHORT", max_digits: 4"#,
+            "SHORT is a numeric value of 5 characters, longer than max_digits 4",
+        );
+    }
+
+    #[test]
+    fn appends_type_suffix() {
+        let env = TestEnv::new(&[("PORT", "42"), ("OFFSET", "-5")]);
+        assert_eq!(expand(&env, r#""PORT", suffix: u64"#).unwrap(), "42_u64");
+        assert_eq!(expand(&env, r#""OFFSET", suffix: i32"#).unwrap(), "-5_i32");
+        assert_eq!(expand(&env, r#""UNSET", 7, suffix: u8"#).unwrap(), "7_u8");
+        assert!(expand_err(&env, r#""PORT", suffix: f64"#)
+            .starts_with("Unsupported type for suffix: f64, expected one of "));
+    }
+
+    #[test]
+    fn packs_utf8_short_strings() {
+        let env = TestEnv::new(&[("SYMBOL", "é"), ("LONG", &"é".repeat(16))]);
+        let expand_shortstring = |args| expand_macro(expand_env_shortstring_macro, &env, args);
+        assert_eq!(
+            expand_shortstring(r#""SYMBOL", utf8: true"#).unwrap(),
+            0xc3a9.to_string()
+        );
+        assert!(expand_shortstring(r#""SYMBOL""#).is_err());
+        assert!(expand_shortstring(r#""LONG", utf8: true"#)
+            .unwrap_err()
+            .contains("it is 32 bytes long, at most 31 are supported"));
+        assert_eq!(
+            expand_shortstring(r#""SYMBOL", utf8: true, endian: little"#).unwrap(),
+            0xa9c3.to_string()
+        );
+        assert_eq!(
+            expand_shortstring(r#""SYMBOL", utf: true"#).unwrap_err(),
+            "Unexpected argument: utf\nnote: in `utf: true`"
+        );
+        assert_eq!(
+            expand_shortstring(r#""UNSET", 'a', 'b'"#).unwrap_err(),
+            "Unexpected argument\nnote: in `'b'`"
+        );
+    }
+
+    #[test]
+    fn expands_build_timestamp() {
+        let reproducible = TestEnv::new(&[("SOURCE_DATE_EPOCH", "1700000000")]);
+        assert_eq!(
+            expand_macro(expand_env_timestamp_macro, &reproducible, "").unwrap(),
+            "1700000000"
+        );
+        let now = expand_macro(expand_env_timestamp_macro, &TestEnv::new(&[]), "").unwrap();
+        assert!(now.parse::<u64>().unwrap() > 1700000000);
+        let invalid = TestEnv::new(&[("SOURCE_DATE_EPOCH", "yesterday")]);
+        assert_eq!(
+            expand_macro(expand_env_timestamp_macro, &invalid, "").unwrap_err(),
+            "SOURCE_DATE_EPOCH=yesterday is not a valid Unix timestamp"
+        );
+        assert!(expand_macro(expand_env_timestamp_macro, &reproducible, "1")
+            .unwrap_err()
+            .starts_with("env_timestamp! takes no arguments"));
+    }
+
+    #[test]
+    fn strips_surrounding_quotes() {
+        let env = TestEnv::new(&[
+            ("DOUBLE", r#""bob""#),
+            ("SINGLE", "'bob'"),
+            ("PLAIN", "bob"),
+            ("MISMATCHED", r#""bob'"#),
+        ]);
+        assert_eq!(
+            expand(&env, r#""DOUBLE", strip_quotes: true"#).unwrap(),
+            r#""bob""#
+        );
+        assert_eq!(
+            expand(&env, r#""SINGLE", strip_quotes: true"#).unwrap(),
+            r#""bob""#
+        );
+        assert_eq!(
+            expand(&env, r#""PLAIN", strip_quotes: true"#).unwrap(),
+            r#""bob""#
+        );
+        assert_eq!(
+            expand(&env, r#""MISMATCHED", strip_quotes: true"#).unwrap(),
+            r#""\"bob'""#
+        );
+        assert_eq!(expand(&env, r#""SINGLE""#).unwrap(), r#""'bob'""#);
+    }
+
+    #[test]
+    fn expands_negative_hex_with_width() {
+        let env = TestEnv::new(&[("MASK", "-1"), ("OFFSET", "-2"), ("WIDE", "256")]);
+        assert_eq!(
+            expand(&env, r#""MASK", kind: hex, width: 8"#).unwrap(),
+            "0xff"
+        );
+        assert_eq!(
+            expand(&env, r#""MASK", kind: hex, width: 16"#).unwrap(),
+            "0xffff"
+        );
+        assert_eq!(
+            expand(&env, r#""OFFSET", kind: hex, width: 8"#).unwrap(),
+            "0xfe"
+        );
+        assert!(expand_err(&env, r#""MASK", kind: hex"#).contains("negative"));
+        assert!(expand(&env, r#""WIDE", kind: hex, width: 8"#).is_err());
+    }
+
+    #[test]
+    fn expands_span_literals() {
+        let env = TestEnv::new(&[("IDS", "1,2,3"), ("EMPTY", ""), ("PIPED", "4|5")]);
+        let expand_span = |args| expand_macro(expand_env_span_macro, &env, args);
+        assert_eq!(expand_span(r#""IDS""#).unwrap(), "[1, 2, 3].span()");
+        assert_eq!(expand_span(r#""EMPTY""#).unwrap(), "[].span()");
+        assert_eq!(
+            expand_span(r#""PIPED", sep: "|""#).unwrap(),
+            "[4, 5].span()"
+        );
+        assert!(expand_span(r#""PIPED""#).is_err());
+    }
+
+    #[test]
+    fn coerces_defaults_to_kind() {
+        let env = TestEnv::new(&[]);
+        let cases = [
+            ("42", "felt", Ok("42"), Ok("42")),
+            (
+                r#""42""#,
+                "felt",
+                Ok("42"),
+                Err("Default \"42\" of UNSET does not match kind: felt"),
+            ),
+            (
+                "true",
+                "felt",
+                Ok("1"),
+                Err("Default true of UNSET does not match kind: felt"),
+            ),
+            ("42", "hex", Ok("0x2a"), Ok("0x2a")),
+            (
+                r#""0x2a""#,
+                "hex",
+                Ok("0x2a"),
+                Err("Default \"0x2a\" of UNSET does not match kind: hex"),
+            ),
+            (
+                "false",
+                "hex",
+                Ok("0x0"),
+                Err("Default false of UNSET does not match kind: hex"),
+            ),
+            (r#""app""#, "bytearray", Ok(r#""app""#), Ok(r#""app""#)),
+            (
+                "42",
+                "bytearray",
+                Ok(r#""42""#),
+                Err("Default 42 of UNSET does not match kind: bytearray"),
+            ),
+            (
+                "false",
+                "bytearray",
+                Ok(r#""false""#),
+                Err("Default false of UNSET does not match kind: bytearray"),
+            ),
+            ("true", "bool", Ok("true"), Ok("true")),
+            (
+                "0",
+                "bool",
+                Ok("false"),
+                Err("Default 0 of UNSET does not match kind: bool"),
+            ),
+            (
+                r#""on""#,
+                "bool",
+                Ok("true"),
+                Err("Default \"on\" of UNSET does not match kind: bool"),
+            ),
+        ];
+        for (default, kind, lenient, strict) in cases {
+            let args = format!(r#""UNSET", default: {}, kind: {}"#, default, kind);
+            assert_eq!(
+                expand(&env, &args).map_err(|errs| errs[0].clone()),
+                lenient.map(str::to_string).map_err(str::to_string),
+                "{}",
+                args
+            );
+            let args = format!("{}, strict_default: true", args);
+            assert_eq!(
+                expand(&env, &args).map_err(|errs| errs[0].clone()),
+                strict.map(str::to_string).map_err(str::to_string),
+                "{}",
+                args
+            );
+        }
+        assert_expand_err(
+            &env,
+            r#""UNSET", default: "abc", kind: felt"#,
+            "UNSET is not numeric (kind: felt requested): abc",
+        );
+    }
+
+    #[test]
+    fn reports_every_violated_constraint() {
+        let env = TestEnv::new(&[("WORKERS", "0"), ("STAGE", "Prod"), ("MASK", "-1")]);
+        assert_eq!(
+            expand_errs(&env, r#""WORKERS", min: 1, nonzero: true, unsigned: u8"#),
+            ["WORKERS=0 must be nonzero", "WORKERS=0 is below min 1"]
+        );
+        assert_eq!(
+            expand_errs(
+                &env,
+                r#""STAGE", one_of: ["dev"], matches: "^[a-z]+$", max_len: 2"#
+            ),
+            [
+                "STAGE=Prod is not one of the allowed values: dev",
+                "STAGE=Prod does not match the pattern ^[a-z]+$",
+                "STAGE is 4 bytes long, longer than max_len 2",
+            ]
+        );
+        let errors = expand_errs(&env, r#""MASK", kind: hex, max: -2"#);
+        assert_eq!(errors.len(), 2, "{:?}", errors);
+        assert!(errors[1].contains("is negative and can't be expanded as hex"));
+    }
+
+    #[test]
+    fn reports_violations_of_every_joined_part() {
+        let env = TestEnv::new(&[("A", "Foo"), ("B", "bar"), ("C", "B-Z")]);
+        assert_eq!(
+            expand_errs(&env, r#""A", "B", "C", join: "-", matches: "^[a-z]+$""#),
+            [
+                "A=Foo does not match the pattern ^[a-z]+$",
+                "C=B-Z does not match the pattern ^[a-z]+$",
+            ]
+        );
+        assert_eq!(
+            expand_errs(
+                &env,
+                r#""A", "B", join: "-", one_of: ["bar"], matches: "^[a-z]+$""#
+            ),
+            [
+                "A=Foo is not one of the allowed values: bar",
+                "A=Foo does not match the pattern ^[a-z]+$",
+            ]
+        );
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn quotes_the_name_that_is_not_valid_utf8() {
+        use std::os::unix::ffi::OsStringExt;
+
+        let mut env = TestEnv::new(&[]);
+        env.vars
+            .insert("PORT".to_string(), OsString::from_vec(vec![0xff]));
+        assert_eq!(
+            expand_err(&env, r#""HOST", "PORT", default: 1"#),
+            "PORT is set but not valid UTF-8\nnote: in `\"PORT\"`"
+        );
+        assert_eq!(
+            expand_err(&env, r#""HOST", fallback_var: "PORT""#),
+            "PORT is set but not valid UTF-8\nnote: in `fallback_var: \"PORT\"`"
+        );
+        assert_eq!(
+            expand_err(&env, r#""APP_PORT", strip_prefix: "APP_""#),
+            "PORT is set but not valid UTF-8\nnote: in `\"APP_PORT\"`"
+        );
+
+        let mut env = TestEnv::new(&[("CAIRO_ENV_PREFIX", "APP_")]);
+        env.vars
+            .insert("APP_PORT".to_string(), OsString::from_vec(vec![0xff]));
+        assert_eq!(
+            expand_err(&env, r#""HOST", "PORT""#),
+            "APP_PORT is set but not valid UTF-8\nnote: in `\"PORT\"`"
+        );
+
+        let mut env = TestEnv::new(&[("PORT", "8080")]);
+        env.vars.insert(
+            "CAIRO_ENV_PREFIX".to_string(),
+            OsString::from_vec(vec![0xff]),
+        );
+        assert_expand_err(
+            &env,
+            r#""PORT""#,
+            "CAIRO_ENV_PREFIX is set but not valid UTF-8",
+        );
+    }
+
+    #[test]
+    fn treats_null_values_as_not_set() {
+        let env = TestEnv::new(&[
+            ("NULL", "null"),
+            ("UPPER", " NULL "),
+            ("NONE", "none"),
+            ("EMPTY", ""),
+            ("PORT", "8080"),
+        ]);
+        let nulls = r#"null_values: ["null", "none"]"#;
+        assert_eq!(
+            expand(&env, &format!(r#""NULL", 1, {}"#, nulls)).unwrap(),
+            "1"
+        );
+        assert_eq!(
+            expand(&env, &format!(r#""UPPER", 1, {}"#, nulls)).unwrap(),
+            "1"
+        );
+        assert_eq!(
+            expand(&env, &format!(r#""NONE", 1, {}"#, nulls)).unwrap(),
+            "1"
+        );
+        assert_eq!(
+            expand(&env, &format!(r#""PORT", 1, {}"#, nulls)).unwrap(),
+            "8080"
+        );
+        assert!(expand_err(&env, &format!(r#""NULL", {}"#, nulls))
+            .starts_with("Environment variable NULL not set"));
+        assert_eq!(expand(&env, r#""NULL", 1"#).unwrap(), r#""null""#);
+        assert_eq!(expand(&env, r#""NONE", 1"#).unwrap(), r#""none""#);
+
+        assert_eq!(
+            expand(&env, r#""EMPTY", 1, allow_empty: true"#).unwrap(),
+            r#""""#
+        );
+        assert_eq!(
+            expand(
+                &env,
+                r#""EMPTY", 1, allow_empty: true, null_values: ["null", ""]"#
+            )
+            .unwrap(),
+            "1"
+        );
     }
 }
@@ -0,0 +1,155 @@
+// SPDX-License-Identifier: MIT
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+/// Name of the package manifest file.
+const MANIFEST_FILE_NAME: &str = "Scarb.toml";
+
+/// Name of the manifest table variables are read from.
+const ENV_TABLE: &str = "tool.env";
+
+/// Looks up a variable in the `[tool.env]` table of the package manifest.
+/// Returns `None` if there is no manifest or its table does not define the variable.
+/// The manifest is read and parsed only once per process.
+pub(crate) fn manifest_var(name: &str) -> Option<String> {
+    static MANIFEST_ENV: OnceLock<HashMap<String, String>> = OnceLock::new();
+
+    MANIFEST_ENV
+        .get_or_init(load_manifest_env)
+        .get(name)
+        .cloned()
+}
+
+/// Loads the variables of the `[tool.env]` table of the package manifest.
+/// Returns an empty map if there is no manifest or it cannot be read.
+fn load_manifest_env() -> HashMap<String, String> {
+    find_manifest()
+        .and_then(|path| std::fs::read_to_string(path).ok())
+        .map(|contents| parse_env_table(&contents))
+        .unwrap_or_default()
+}
+
+/// Finds the package manifest, either as given by Scarb or walking up from the current directory.
+fn find_manifest() -> Option<PathBuf> {
+    if let Some(manifest_path) = std::env::var_os("SCARB_MANIFEST_PATH") {
+        return Some(PathBuf::from(manifest_path));
+    }
+    let current_dir = std::env::current_dir().ok()?;
+    current_dir
+        .ancestors()
+        .map(|dir| dir.join(MANIFEST_FILE_NAME))
+        .find(|path| path.is_file())
+}
+
+/// Parses the `[tool.env]` table of a manifest.
+/// Only `KEY = "string"` and `KEY = 123` entries are read, other values and tables are ignored.
+///
+/// This is not a full TOML parser: multi-line values, inline tables and quoted keys are not supported.
+fn parse_env_table(contents: &str) -> HashMap<String, String> {
+    let mut in_env_table = false;
+    let mut vars = HashMap::new();
+    for line in contents.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('[') {
+            let header = header.split('#').next().unwrap_or_default().trim_end();
+            in_env_table = header
+                .strip_suffix(']')
+                .is_some_and(|table| table.trim() == ENV_TABLE);
+            continue;
+        }
+        if !in_env_table {
+            continue;
+        }
+        let Some((key, value)) = line.split_once('=') else {
+            continue;
+        };
+        if let Some(value) = parse_simple_value(value.trim()) {
+            vars.insert(key.trim().to_string(), value);
+        }
+    }
+    vars
+}
+
+/// Parses a string or integer value, ignoring a trailing comment.
+/// Returns `None` for any other kind of value.
+fn parse_simple_value(value: &str) -> Option<String> {
+    if let Some(literal) = value.strip_prefix('\'') {
+        // Literal strings have no escapes.
+        let (string, _) = literal.split_once('\'')?;
+        return Some(string.to_string());
+    }
+    if let Some(basic) = value.strip_prefix('"') {
+        return parse_basic_string(basic);
+    }
+
+    let integer = value.split('#').next().unwrap_or_default().trim_end();
+    let is_integer = integer.starts_with(|c: char| c.is_ascii_digit() || c == '+' || c == '-')
+        && integer
+            .bytes()
+            .all(|byte| byte.is_ascii_alphanumeric() || matches!(byte, b'_' | b'+' | b'-'));
+    is_integer.then(|| integer.to_string())
+}
+
+/// Parses the rest of a basic string after the opening quote, resolving simple escapes.
+/// Returns `None` if the string is not terminated or has an unsupported escape.
+fn parse_basic_string(basic: &str) -> Option<String> {
+    let mut string = String::with_capacity(basic.len());
+    let mut chars = basic.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' => return Some(string),
+            '\\' => string.push(match chars.next()? {
+                'n' => '\n',
+                't' => '\t',
+                'r' => '\r',
+                '"' => '"',
+                '\\' => '\\',
+                _ => return None,
+            }),
+            _ => string.push(c),
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_simple_values_of_env_table() {
+        let vars = parse_env_table(
+            r#"
+[package]
+name = "app"
+
+[tool.env] # build settings
+PORT = 8080
+NAME = "app"
+QUOTED = "say \"hi\""
+LITERAL = 'C:\path'
+NEGATIVE = -5 # offset
+ENABLED = true
+LIST = [1, 2]
+
+[tool.other]
+OTHER = 1
+"#,
+        );
+        assert_eq!(vars.len(), 5);
+        assert_eq!(vars["PORT"], "8080");
+        assert_eq!(vars["NAME"], "app");
+        assert_eq!(vars["QUOTED"], "say \"hi\"");
+        assert_eq!(vars["LITERAL"], r"C:\path");
+        assert_eq!(vars["NEGATIVE"], "-5");
+    }
+
+    #[test]
+    fn ignores_manifest_without_env_table() {
+        assert!(parse_env_table("[package]\nPORT = 8080\n").is_empty());
+    }
+}
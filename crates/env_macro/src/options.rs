@@ -0,0 +1,964 @@
+// SPDX-License-Identifier: MIT
+// Based on the code from Alexandria library (https://github.com/keep-starknet-strange/alexandria)
+// Copyright (c) 2025 Alexandria Contributors
+
+use std::collections::{HashMap, HashSet};
+
+use cairo_lang_macro::{Diagnostic, TokenStream};
+use cairo_lang_parser::utils::SimpleParserDatabase;
+use cairo_lang_syntax::node::ast::{
+    Arg, ArgClause, BinaryOperator, Expr, OptionFixedSizeArraySize, PathSegment, UnaryOperator,
+};
+use cairo_lang_syntax::node::{Terminal, TypedSyntaxNode};
+use cairo_lang_utils::Upcast;
+use num_bigint::{BigInt, Sign};
+use regex::Regex;
+
+use crate::dotenv;
+use crate::syntax::error_at;
+use crate::value::{parse_env_value_as, string_literal, EnvValue, MAX_HEX_WIDTH};
+use crate::{package_path, Env};
+
+/// Options of the `env!` macro, given after the environment variable name.
+#[derive(Default)]
+pub(crate) struct EnvOptions {
+    /// The default value, given either positionally or as `default: value`.
+    pub(crate) default: Option<Expr>,
+    /// Whether to trim surrounding whitespace from string values, given as `trim: true`.
+    /// Numeric values are always trimmed.
+    pub(crate) trim: bool,
+    /// Whether to remove a matching pair of quotes surrounding the value, given as `strip_quotes: true`.
+    pub(crate) strip_quotes: bool,
+    /// The case mapping applied to a string value, given as `transform: lowercase`.
+    pub(crate) transform: Option<Transform>,
+    /// The separator all the variables are joined with into a single string, given as `join: "-"`.
+    pub(crate) join: Option<String>,
+    /// Whether a numeric value must not be zero, given as `nonzero: true`.
+    pub(crate) nonzero: bool,
+    /// The variable to look up if none of the requested ones is set, given as `fallback_var: "SECONDARY"`.
+    pub(crate) fallback_var: Option<String>,
+    /// Whether the value is the path of a file holding the actual value, given as `from_file: true`.
+    pub(crate) from_file: bool,
+    /// Whether to fall back to the `[tool.env]` table of `Scarb.toml`, given as `toml: true`.
+    pub(crate) toml: bool,
+    /// Whether to expand `${VAR}` references in the value, given as `interpolate: true`.
+    pub(crate) interpolate: bool,
+    /// Whether an empty or whitespace-only value is expanded to a string instead of being treated as not set,
+    /// given as `allow_empty: true`.
+    allow_empty: bool,
+    /// The values that count as not set, compared ignoring case, given as `null_values: ["null", "none"]`.
+    null_values: Vec<String>,
+    /// Whether `one_of:` values are compared ignoring case, given as `case_insensitive: true`.
+    pub(crate) case_insensitive: bool,
+    /// The integer type the value must fit into, given as `signed: i64` or `unsigned: u16`.
+    pub(crate) integer_type: Option<IntegerType>,
+    /// The integer type appended to a numeric literal, given as `suffix: u64`.
+    pub(crate) suffix: Option<IntegerType>,
+    /// Whether to emit a warning when the default is used, given as `warn_on_default: true`.
+    pub(crate) warn_on_default: bool,
+    /// Whether to log the resolved value and its source to stderr, given as `debug: true`.
+    pub(crate) debug: bool,
+    /// Whether the `.env` file takes precedence over the process environment, given as `prefer: dotenv`.
+    pub(crate) prefer_dotenv: bool,
+    /// The diagnostic message to report if the variable is not set and there is no default, given as `message: "..."`.
+    pub(crate) message: Option<String>,
+    /// The inclusive lower bound of a numeric value, given as `min: value`.
+    pub(crate) min: Option<BigInt>,
+    /// The inclusive upper bound of a numeric value, given as `max: value`.
+    pub(crate) max: Option<BigInt>,
+    /// The inclusive lower bound of the length of a string value in bytes, given as `min_len: value`.
+    pub(crate) min_len: Option<usize>,
+    /// The inclusive upper bound of the length of a string value in bytes, given as `max_len: value`.
+    pub(crate) max_len: Option<usize>,
+    /// The maximum length in characters of a value parsed as a number, given as `max_digits: value`.
+    pub(crate) max_digits: Option<usize>,
+    /// The allowed string values, given as `one_of: ["a", "b"]`.
+    pub(crate) one_of: Option<Vec<String>>,
+    /// The pattern a string value must match, given as `matches: "^[a-z]+$"`.
+    pub(crate) matches: Option<Regex>,
+    /// Whether to reduce numeric values that do not fit into `felt252` modulo the field prime, given as `wrapping: true`.
+    pub(crate) wrapping: bool,
+    /// The field prime numeric values must fit into instead of the STARK prime, given as `prime: value`.
+    pub(crate) prime: Option<BigInt>,
+    /// The prefix to remove from a variable name to retry the lookup if it is not set, given as `strip_prefix: "PREFIX_"`.
+    pub(crate) strip_prefix: Option<String>,
+    /// The bit width negative values are expanded at as two's complement with `kind: hex`, given as `width: 8`.
+    pub(crate) width: Option<u32>,
+    /// The kind of literal to expand the value to instead of detecting it, given as `kind: bytearray`.
+    pub(crate) kind: Option<ValueKind>,
+    /// Whether a default that doesn't match `kind:` is an error instead of being coerced, given as `strict_default: true`.
+    pub(crate) strict_default: bool,
+    /// The variables of the `.env` file given as `dotenv: "config/prod.env"`, used instead of the closest `.env` file.
+    pub(crate) dotenv: Option<HashMap<String, String>>,
+}
+
+impl EnvOptions {
+    /// Checks whether a resolved value counts as set.
+    /// Empty or whitespace-only values don't unless `allow_empty:` is given, and neither do values listed in `null_values:`,
+    /// which are compared ignoring case and surrounding whitespace.
+    pub(crate) fn counts_as_set(&self, val: &str) -> bool {
+        let val = val.trim();
+        (self.allow_empty || !val.is_empty())
+            && !self
+                .null_values
+                .iter()
+                .any(|null_value| null_value.trim().eq_ignore_ascii_case(val))
+    }
+}
+
+/// Kind of literal a value is expanded to, overriding the automatic detection.
+/// Cairo reserves the `as` keyword, so it is given as `kind:`.
+#[derive(Clone, Copy)]
+pub(crate) enum ValueKind {
+    /// A numeric literal, given as `kind: felt`.
+    Felt,
+    /// A `ByteArray` literal, given as `kind: bytearray`.
+    ByteArray,
+    /// A `bool` literal, given as `kind: bool`.
+    Bool,
+    /// A hexadecimal numeric literal, given as `kind: hex`.
+    Hex,
+}
+
+impl ValueKind {
+    /// Names of the supported kinds, as listed in diagnostics.
+    const NAMES: [&'static str; 4] = ["felt", "bytearray", "bool", "hex"];
+
+    /// Returns the kind with the given name, or `None` if it is not supported.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "felt" => Some(ValueKind::Felt),
+            "bytearray" => Some(ValueKind::ByteArray),
+            "bool" => Some(ValueKind::Bool),
+            "hex" => Some(ValueKind::Hex),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the kind, as given in `kind:`.
+    pub(crate) fn name(self) -> &'static str {
+        match self {
+            ValueKind::Felt => "felt",
+            ValueKind::ByteArray => "bytearray",
+            ValueKind::Bool => "bool",
+            ValueKind::Hex => "hex",
+        }
+    }
+}
+
+/// Byte order used to pack bytes into a `felt252`, given as `endian: little`.
+#[derive(Clone, Copy)]
+pub(crate) enum Endian {
+    /// The first byte is the most significant one, like in short string literals, given as `endian: big`.
+    Big,
+    /// The first byte is the least significant one, given as `endian: little`.
+    Little,
+}
+
+impl Endian {
+    /// Returns the byte order with the given name, or `None` if it is not supported.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "big" => Some(Endian::Big),
+            "little" => Some(Endian::Little),
+            _ => None,
+        }
+    }
+
+    /// Packs the bytes into a number in this byte order.
+    pub(crate) fn pack(self, bytes: &[u8]) -> BigInt {
+        match self {
+            Endian::Big => BigInt::from_bytes_be(Sign::Plus, bytes),
+            Endian::Little => BigInt::from_bytes_le(Sign::Plus, bytes),
+        }
+    }
+}
+
+/// Parses an argument of the packing macros, which can only be `endian:`.
+/// Returns the byte order or a diagnostic error if the argument is not `endian:` or names an unsupported byte order.
+pub(crate) fn get_endian_option(
+    db: &SimpleParserDatabase,
+    arg: &Arg,
+) -> Result<Endian, Diagnostic> {
+    let ArgClause::Named(arg_clause) = arg.arg_clause(db.upcast()) else {
+        return Err(error_at(db, arg, "Unexpected argument"));
+    };
+    let key = arg_clause.name(db.upcast()).text(db.upcast());
+    if key != "endian" {
+        return Err(error_at(
+            db,
+            &arg_clause,
+            format!("Unexpected argument: {}", key),
+        ));
+    }
+    let value = arg_clause.value(db.upcast());
+    let name = get_identifier_option(db, &key, &value)?;
+    Endian::from_name(&name).ok_or_else(|| {
+        error_at(
+            db,
+            &value,
+            format!("Unsupported endian: {}, expected one of big, little", name),
+        )
+    })
+}
+
+/// Case mapping applied to a string value, given as `transform: lowercase`.
+#[derive(Clone, Copy)]
+pub(crate) enum Transform {
+    /// Maps ASCII letters to lowercase, given as `transform: lowercase`.
+    Lowercase,
+    /// Maps ASCII letters to uppercase, given as `transform: uppercase`.
+    Uppercase,
+}
+
+impl Transform {
+    /// Returns the transform with the given name, or `None` if it is not supported.
+    fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "lowercase" => Some(Transform::Lowercase),
+            "uppercase" => Some(Transform::Uppercase),
+            _ => None,
+        }
+    }
+
+    /// Returns the name of the transform.
+    fn name(&self) -> &'static str {
+        match self {
+            Transform::Lowercase => "lowercase",
+            Transform::Uppercase => "uppercase",
+        }
+    }
+
+    /// Applies the transform to a string value.
+    /// Returns a diagnostic error naming the variable if the value is not a string.
+    pub(crate) fn apply(
+        &self,
+        env_var_name: &str,
+        env_value: EnvValue,
+    ) -> Result<EnvValue, Diagnostic> {
+        match env_value {
+            EnvValue::String(string_val) => Ok(EnvValue::String(match self {
+                Transform::Lowercase => string_val.to_ascii_lowercase(),
+                Transform::Uppercase => string_val.to_ascii_uppercase(),
+            })),
+            // Expressions can't be evaluated at expansion time.
+            EnvValue::Expr(_) => Ok(env_value),
+            EnvValue::Numeric(_) | EnvValue::Bool(_) => Err(Diagnostic::error(format!(
+                "{}={} is not a string, transform: {} requires a string value",
+                env_var_name,
+                env_value.to_literal(),
+                self.name()
+            ))),
+        }
+    }
+}
+
+/// Integer type a numeric value must fit into, given as `signed: i64` or `unsigned: u16`.
+#[derive(Clone, Copy)]
+pub(crate) struct IntegerType {
+    /// Name of the Cairo type.
+    pub(crate) name: &'static str,
+    /// Width of the type in bits.
+    bits: u32,
+    /// Whether the type is signed.
+    signed: bool,
+}
+
+impl IntegerType {
+    /// Signed integer types accepted by `signed:`.
+    const SIGNED: [IntegerType; 5] = [
+        IntegerType::signed("i8", 8),
+        IntegerType::signed("i16", 16),
+        IntegerType::signed("i32", 32),
+        IntegerType::signed("i64", 64),
+        IntegerType::signed("i128", 128),
+    ];
+
+    /// Unsigned integer types accepted by `unsigned:`.
+    const UNSIGNED: [IntegerType; 4] = [
+        IntegerType::unsigned("u8", 8),
+        IntegerType::unsigned("u16", 16),
+        IntegerType::unsigned("u32", 32),
+        IntegerType::unsigned("u64", 64),
+    ];
+
+    /// Integer types accepted by `suffix:`, which Cairo supports as literal suffixes.
+    const SUFFIXES: [IntegerType; 12] = [
+        IntegerType::signed("i8", 8),
+        IntegerType::signed("i16", 16),
+        IntegerType::signed("i32", 32),
+        IntegerType::signed("i64", 64),
+        IntegerType::signed("i128", 128),
+        IntegerType::unsigned("u8", 8),
+        IntegerType::unsigned("u16", 16),
+        IntegerType::unsigned("u32", 32),
+        IntegerType::unsigned("u64", 64),
+        IntegerType::unsigned("u128", 128),
+        IntegerType::unsigned("u256", 256),
+        IntegerType::unsigned("usize", 32),
+    ];
+
+    /// Returns the signed integer type with the given name and width.
+    const fn signed(name: &'static str, bits: u32) -> Self {
+        IntegerType {
+            name,
+            bits,
+            signed: true,
+        }
+    }
+
+    /// Returns the unsigned integer type with the given name and width.
+    const fn unsigned(name: &'static str, bits: u32) -> Self {
+        IntegerType {
+            name,
+            bits,
+            signed: false,
+        }
+    }
+
+    /// Returns the smallest value of the type.
+    pub(crate) fn min(&self) -> BigInt {
+        if self.signed {
+            -(BigInt::from(1) << (self.bits - 1))
+        } else {
+            BigInt::ZERO
+        }
+    }
+
+    /// Returns the largest value of the type.
+    pub(crate) fn max(&self) -> BigInt {
+        let value_bits = if self.signed {
+            self.bits - 1
+        } else {
+            self.bits
+        };
+        (BigInt::from(1) << value_bits) - 1
+    }
+}
+
+/// Parses the value of a named argument that is one of the given integer types, like `i64`.
+/// Returns the integer type or a diagnostic error listing the supported types.
+fn get_integer_type_option(
+    db: &SimpleParserDatabase,
+    key: &str,
+    expr: &Expr,
+    integer_types: &[IntegerType],
+) -> Result<IntegerType, Diagnostic> {
+    let name = get_identifier_option(db, key, expr)?;
+    integer_types
+        .iter()
+        .find(|integer_type| integer_type.name == name)
+        .copied()
+        .ok_or_else(|| {
+            let names: Vec<&str> = integer_types
+                .iter()
+                .map(|integer_type| integer_type.name)
+                .collect();
+            error_at(
+                db,
+                expr,
+                format!(
+                    "Unsupported type for {}: {}, expected one of {}",
+                    key,
+                    name,
+                    names.join(", ")
+                ),
+            )
+        })
+}
+
+/// Parses the arguments following the environment variable name into [`EnvOptions`].
+/// Returns a diagnostic error if an unknown or duplicate named argument is given.
+pub(crate) fn parse_env_options(
+    db: &SimpleParserDatabase,
+    env: &impl Env,
+    args: &[Arg],
+) -> Result<EnvOptions, Diagnostic> {
+    let mut options = EnvOptions::default();
+    let mut seen_keys = HashSet::new();
+    for arg in args {
+        let arg_clause = match arg.arg_clause(db.upcast()) {
+            ArgClause::Unnamed(arg_clause) => {
+                // A positional default counts as `default:`.
+                if !seen_keys.insert("default".to_string()) {
+                    return Err(error_at(db, arg, "Duplicate argument: default"));
+                }
+                options.default = Some(arg_clause.value(db.upcast()));
+                continue;
+            }
+            ArgClause::Named(arg_clause) => arg_clause,
+            ArgClause::FieldInitShorthand(_) => {
+                return Err(error_at(db, arg, "Unexpected shorthand argument"))
+            }
+        };
+
+        let key = arg_clause.name(db.upcast()).text(db.upcast());
+        let value = arg_clause.value(db.upcast());
+        if !seen_keys.insert(key.to_string()) {
+            return Err(error_at(
+                db,
+                &arg_clause,
+                format!("Duplicate argument: {}", key),
+            ));
+        }
+        match key.as_str() {
+            "default" => options.default = Some(value),
+            "trim" => options.trim = get_bool_option(&key, &value)?,
+            "strip_quotes" => options.strip_quotes = get_bool_option(&key, &value)?,
+            "transform" => {
+                let name = get_identifier_option(db, &key, &value)?;
+                let transform = Transform::from_name(&name).ok_or_else(|| {
+                    error_at(
+                        db,
+                        &value,
+                        format!(
+                            "Unsupported transform: {}, expected one of lowercase, uppercase",
+                            name
+                        ),
+                    )
+                })?;
+                options.transform = Some(transform);
+            }
+            "join" => options.join = Some(get_string_option(db, &key, &value)?),
+            "nonzero" => options.nonzero = get_bool_option(&key, &value)?,
+            "fallback_var" => options.fallback_var = Some(get_string_option(db, &key, &value)?),
+            "from_file" => options.from_file = get_bool_option(&key, &value)?,
+            "toml" => options.toml = get_bool_option(&key, &value)?,
+            "interpolate" => options.interpolate = get_bool_option(&key, &value)?,
+            "allow_empty" => options.allow_empty = get_bool_option(&key, &value)?,
+            "strict_default" => options.strict_default = get_bool_option(&key, &value)?,
+            "null_values" => options.null_values = get_string_array_option(db, &key, &value)?,
+            "case_insensitive" => options.case_insensitive = get_bool_option(&key, &value)?,
+            "warn_on_default" => options.warn_on_default = get_bool_option(&key, &value)?,
+            "debug" => options.debug = get_bool_option(&key, &value)?,
+            "prefer" => {
+                let name = get_identifier_option(db, &key, &value)?;
+                options.prefer_dotenv = match name.as_str() {
+                    "env" => false,
+                    "dotenv" => true,
+                    _ => {
+                        return Err(error_at(
+                            db,
+                            &value,
+                            format!("Unsupported prefer: {}, expected one of env, dotenv", name),
+                        ))
+                    }
+                };
+            }
+            "signed" | "unsigned" if options.integer_type.is_some() => {
+                return Err(error_at(
+                    db,
+                    &arg_clause,
+                    "signed: and unsigned: can't be combined, give the single integer type the value must fit into",
+                ));
+            }
+            "signed" => {
+                options.integer_type = Some(get_integer_type_option(
+                    db,
+                    &key,
+                    &value,
+                    &IntegerType::SIGNED,
+                )?)
+            }
+            "unsigned" => {
+                options.integer_type = Some(get_integer_type_option(
+                    db,
+                    &key,
+                    &value,
+                    &IntegerType::UNSIGNED,
+                )?)
+            }
+            "suffix" => {
+                options.suffix = Some(get_integer_type_option(
+                    db,
+                    &key,
+                    &value,
+                    &IntegerType::SUFFIXES,
+                )?)
+            }
+            "message" => options.message = Some(get_string_option(db, &key, &value)?),
+            "wrapping" => options.wrapping = get_bool_option(&key, &value)?,
+            "prime" => {
+                let prime = get_numeric_option(db, &key, &value)?;
+                if prime < BigInt::from(2) {
+                    return Err(error_at(db, &value, "prime must be at least 2"));
+                }
+                options.prime = Some(prime);
+            }
+            "strip_prefix" => options.strip_prefix = Some(get_string_option(db, &key, &value)?),
+            "dotenv" => {
+                let path = get_string_option(db, &key, &value)?;
+                let dotenv_vars =
+                    dotenv::read_dotenv_file(&package_path(env, &path)).map_err(|err| {
+                        error_at(
+                            db,
+                            &value,
+                            format!("Failed to read dotenv file {}: {}", path, err),
+                        )
+                    })?;
+                options.dotenv = Some(dotenv_vars);
+            }
+            "kind" => {
+                let name = get_identifier_option(db, &key, &value)?;
+                let kind = ValueKind::from_name(&name).ok_or_else(|| {
+                    error_at(
+                        db,
+                        &value,
+                        format!(
+                            "Unsupported kind: {}, expected one of {}",
+                            name,
+                            ValueKind::NAMES.join(", ")
+                        ),
+                    )
+                })?;
+                options.kind = Some(kind);
+            }
+            "min" => options.min = Some(get_numeric_option(db, &key, &value)?),
+            "max" => options.max = Some(get_numeric_option(db, &key, &value)?),
+            "min_len" => options.min_len = Some(get_length_option(db, &key, &value)?),
+            "max_len" => options.max_len = Some(get_length_option(db, &key, &value)?),
+            "max_digits" => options.max_digits = Some(get_length_option(db, &key, &value)?),
+            "matches" => {
+                let pattern = get_string_option(db, &key, &value)?;
+                let regex = Regex::new(&pattern).map_err(|err| {
+                    error_at(db, &value, format!("Invalid matches pattern: {}", err))
+                })?;
+                options.matches = Some(regex);
+            }
+            "width" => {
+                let width = get_numeric_option(db, &key, &value)?;
+                options.width = Some(
+                    u32::try_from(&width)
+                        .ok()
+                        .filter(|width| (1..=MAX_HEX_WIDTH).contains(width))
+                        .ok_or_else(|| {
+                            error_at(
+                                db,
+                                &value,
+                                format!("width must be between 1 and {}", MAX_HEX_WIDTH),
+                            )
+                        })?,
+                );
+            }
+            "one_of" => {
+                let allowed = get_string_array_option(db, &key, &value)?;
+                if allowed.is_empty() {
+                    return Err(error_at(
+                        db,
+                        &value,
+                        "one_of must be a non-empty list of allowed values",
+                    ));
+                }
+                options.one_of = Some(allowed);
+            }
+            _ => {
+                return Err(error_at(
+                    db,
+                    &arg_clause,
+                    format!("Unexpected argument: {}", key),
+                ))
+            }
+        }
+    }
+    if options.width.is_some() && !matches!(options.kind, Some(ValueKind::Hex)) {
+        return Err(Diagnostic::error("width: requires kind: hex"));
+    }
+    if options.join.is_some() {
+        // The joined value is always a string and every name is looked up on its own,
+        // so options changing the type of the value or the names looked up don't apply.
+        let conflicting = [
+            (
+                "kind:",
+                options
+                    .kind
+                    .is_some_and(|kind| !matches!(kind, ValueKind::ByteArray)),
+            ),
+            ("fallback_var:", options.fallback_var.is_some()),
+            ("strip_prefix:", options.strip_prefix.is_some()),
+            ("min:", options.min.is_some()),
+            ("max:", options.max.is_some()),
+            ("nonzero:", options.nonzero),
+            ("signed: or unsigned:", options.integer_type.is_some()),
+            ("suffix:", options.suffix.is_some()),
+            ("prime:", options.prime.is_some()),
+            ("wrapping:", options.wrapping),
+        ];
+        if let Some((name, _)) = conflicting.iter().find(|(_, given)| *given) {
+            return Err(Diagnostic::error(format!(
+                "join: can't be combined with {}, the joined value is always a string",
+                name
+            )));
+        }
+    }
+    Ok(options)
+}
+
+/// Parses the value of a boolean named argument.
+/// Returns the value or a diagnostic error naming the argument if it is not a `bool` literal.
+pub(crate) fn get_bool_option(key: &str, expr: &Expr) -> Result<bool, Diagnostic> {
+    match expr {
+        Expr::True(_) => Ok(true),
+        Expr::False(_) => Ok(false),
+        _ => Err(Diagnostic::error(format!(
+            "Expected boolean value for {}",
+            key
+        ))),
+    }
+}
+
+/// Parses the value of a numeric named argument.
+/// Returns the value or a diagnostic error naming the argument if it is not a numeric literal.
+pub(crate) fn get_numeric_option(
+    db: &SimpleParserDatabase,
+    key: &str,
+    expr: &Expr,
+) -> Result<BigInt, Diagnostic> {
+    get_numeric_literal(db, expr)
+        .map_err(|_| error_at(db, expr, format!("Expected numeric value for {}", key)))
+}
+
+/// Parses the value of a length named argument, like `max_len: 31`.
+/// Returns the length or a diagnostic error naming the argument if it is not a non-negative numeric literal.
+fn get_length_option(
+    db: &SimpleParserDatabase,
+    key: &str,
+    expr: &Expr,
+) -> Result<usize, Diagnostic> {
+    get_numeric_option(db, key, expr)?.try_into().map_err(|_| {
+        error_at(
+            db,
+            expr,
+            format!("Expected non-negative length for {}", key),
+        )
+    })
+}
+
+/// Parses the value of a string named argument.
+/// Returns the value or a diagnostic error naming the argument if it is not a string literal.
+pub(crate) fn get_string_option(
+    db: &SimpleParserDatabase,
+    key: &str,
+    expr: &Expr,
+) -> Result<String, Diagnostic> {
+    match expr {
+        Expr::String(string_lit) => string_lit
+            .string_value(db.upcast())
+            .ok_or_else(|| error_at(db, expr, format!("Failed to parse string for {}", key))),
+        _ => Err(error_at(
+            db,
+            expr,
+            format!("Expected string value for {}", key),
+        )),
+    }
+}
+
+/// Parses the value of a named argument that is an identifier, like `felt`.
+/// Returns the identifier or a diagnostic error naming the argument if it is not a single identifier.
+pub(crate) fn get_identifier_option(
+    db: &SimpleParserDatabase,
+    key: &str,
+    expr: &Expr,
+) -> Result<String, Diagnostic> {
+    if let Expr::Path(path) = expr {
+        if let [PathSegment::Simple(segment)] = path.elements(db.upcast()).as_slice() {
+            return Ok(segment.ident(db.upcast()).text(db.upcast()).to_string());
+        }
+    }
+    Err(error_at(
+        db,
+        expr,
+        format!("Expected identifier for {}", key),
+    ))
+}
+
+/// Parses the value of a named argument that is an array of strings, like `["a", "b"]`.
+/// Returns the strings or a diagnostic error naming the argument if it is not an array of string literals.
+pub(crate) fn get_string_array_option(
+    db: &SimpleParserDatabase,
+    key: &str,
+    expr: &Expr,
+) -> Result<Vec<String>, Diagnostic> {
+    let expected_error = || error_at(db, expr, format!("Expected array of strings for {}", key));
+    let Expr::FixedSizeArray(array) = expr else {
+        return Err(expected_error());
+    };
+    if !matches!(array.size(db.upcast()), OptionFixedSizeArraySize::Empty(_)) {
+        return Err(expected_error());
+    }
+
+    array
+        .exprs(db.upcast())
+        .elements(db.upcast())
+        .iter()
+        .map(|element| match element {
+            Expr::String(string_lit) => string_lit
+                .string_value(db.upcast())
+                .ok_or_else(|| error_at(db, element, format!("Failed to parse string in {}", key))),
+            _ => Err(error_at(db, element, format!("Expected string in {}", key))),
+        })
+        .collect()
+}
+
+/// Parses the default value of the macro.
+/// Returns the default value, either numeric, string or boolean, or a diagnostic error if there was a parsing error.
+pub(crate) fn get_default_value(
+    db: &SimpleParserDatabase,
+    base_expr: &Expr,
+) -> Result<EnvValue, Diagnostic> {
+    match base_expr {
+        Expr::String(base_lit) => base_lit
+            .string_value(db.upcast())
+            .map(EnvValue::String)
+            .ok_or_else(|| error_at(db, base_expr, "Failed to parse string default")),
+        Expr::True(_) => Ok(EnvValue::Bool(true)),
+        Expr::False(_) => Ok(EnvValue::Bool(false)),
+        // Numeric literals and constant arithmetic are folded, other expressions like constants are emitted verbatim.
+        _ => match eval_constant_expr(db, base_expr)? {
+            Some(numeric_val) => Ok(EnvValue::Numeric(numeric_val)),
+            None => Ok(EnvValue::Expr(
+                base_expr
+                    .as_syntax_node()
+                    .get_text_without_trivia(db.upcast()),
+            )),
+        },
+    }
+}
+
+/// Coerces a default value to the kind given as `kind:`, so it expands to the same type as a resolved value would.
+/// A numeric default becomes its decimal string under `kind: bytearray`, or `false` and `true` for 0 and 1 under `kind: bool`,
+/// while a string default is parsed like a variable value under the other kinds. Expression defaults are kept verbatim.
+/// Returns a diagnostic error if the default can't be coerced, or with `strict_default: true` if it doesn't already match.
+pub(crate) fn coerce_default_value(
+    env_var_name: &str,
+    env_value: EnvValue,
+    kind: Option<ValueKind>,
+    strict_default: bool,
+) -> Result<EnvValue, Diagnostic> {
+    let Some(kind) = kind else {
+        return Ok(env_value);
+    };
+    let matches_kind = matches!(
+        (&env_value, kind),
+        (EnvValue::Expr(_), _)
+            | (EnvValue::Numeric(_), ValueKind::Felt | ValueKind::Hex)
+            | (EnvValue::String(_), ValueKind::ByteArray)
+            | (EnvValue::Bool(_), ValueKind::Bool)
+    );
+    if matches_kind {
+        return Ok(env_value);
+    }
+    if strict_default {
+        return Err(Diagnostic::error(format!(
+            "Default {} of {} does not match kind: {}",
+            env_value.to_literal(),
+            env_var_name,
+            kind.name()
+        )));
+    }
+
+    match env_value {
+        EnvValue::Numeric(numeric_val) => match kind {
+            ValueKind::ByteArray => Ok(EnvValue::String(numeric_val.to_string())),
+            _ => parse_env_value_as(env_var_name, &numeric_val.to_string(), kind),
+        },
+        EnvValue::String(string_val) => parse_env_value_as(env_var_name, &string_val, kind),
+        EnvValue::Bool(bool_val) => match kind {
+            ValueKind::ByteArray => Ok(EnvValue::String(bool_val.to_string())),
+            _ => Ok(EnvValue::Numeric(BigInt::from(bool_val))),
+        },
+        EnvValue::Expr(_) => Ok(env_value),
+    }
+}
+
+/// Parses a numeric literal expression, optionally negated like `-5` or computed like `60 * 60`.
+/// Returns the numeric value or a diagnostic error if the expression is not a constant numeric expression.
+pub(crate) fn get_numeric_literal(
+    db: &SimpleParserDatabase,
+    expr: &Expr,
+) -> Result<BigInt, Diagnostic> {
+    eval_constant_expr(db, expr)?.ok_or_else(|| error_at(db, expr, "Expected numeric default"))
+}
+
+/// Evaluates a constant integer expression built from numeric literals, `+`, `-`, `*`, `/` and parentheses.
+/// Division truncates towards zero, like for Cairo integer types.
+/// Returns `None` if the expression is not constant, or a diagnostic error if a literal is malformed or a division by zero occurs.
+fn eval_constant_expr(
+    db: &SimpleParserDatabase,
+    expr: &Expr,
+) -> Result<Option<BigInt>, Diagnostic> {
+    match expr {
+        Expr::Literal(base_lit) => base_lit
+            .numeric_value(db.upcast())
+            .map(Some)
+            .ok_or_else(|| error_at(db, expr, "Failed to parse numeric default")),
+        Expr::Parenthesized(parenthesized) => {
+            eval_constant_expr(db, &parenthesized.expr(db.upcast()))
+        }
+        Expr::Unary(unary) if matches!(unary.op(db.upcast()), UnaryOperator::Minus(_)) => {
+            Ok(eval_constant_expr(db, &unary.expr(db.upcast()))?.map(|val| -val))
+        }
+        Expr::Binary(binary) => {
+            let (Some(lhs), Some(rhs)) = (
+                eval_constant_expr(db, &binary.lhs(db.upcast()))?,
+                eval_constant_expr(db, &binary.rhs(db.upcast()))?,
+            ) else {
+                return Ok(None);
+            };
+            match binary.op(db.upcast()) {
+                BinaryOperator::Plus(_) => Ok(Some(lhs + rhs)),
+                BinaryOperator::Minus(_) => Ok(Some(lhs - rhs)),
+                BinaryOperator::Mul(_) => Ok(Some(lhs * rhs)),
+                BinaryOperator::Div(_) if rhs == BigInt::ZERO => {
+                    Err(error_at(db, expr, "Division by zero in default value"))
+                }
+                BinaryOperator::Div(_) => Ok(Some(lhs / rhs)),
+                _ => Ok(None),
+            }
+        }
+        _ => Ok(None),
+    }
+}
+
+/// Parses the second argument of the string macro, which is the default value.
+/// Returns the default value as a `ByteArray` literal or a diagnostic error if there was a parsing error.
+pub(crate) fn get_string_default_value(
+    db: &SimpleParserDatabase,
+    arg_clause: &ArgClause,
+) -> Result<TokenStream, Diagnostic> {
+    let base_expr = match arg_clause {
+        ArgClause::Unnamed(arg_clause) => arg_clause.value(db.upcast()),
+        _ => {
+            return Err(error_at(
+                db,
+                arg_clause,
+                "Expected unnamed default argument",
+            ))
+        }
+    };
+
+    if let Expr::String(base_lit) = base_expr {
+        let string_val = base_lit
+            .string_value(db.upcast())
+            .ok_or_else(|| error_at(db, &base_lit, "Failed to parse string default"))?;
+        Ok(TokenStream::new(string_literal(string_val.as_bytes())))
+    } else {
+        Err(error_at(db, &base_expr, "Expected string default"))
+    }
+}
+
+/// Parses the second argument of the numeric macros, like `env_u256!`, which is the default value.
+/// Returns the default value or a diagnostic error if there was a parsing error.
+pub(crate) fn get_numeric_default_value(
+    db: &SimpleParserDatabase,
+    arg_clause: &ArgClause,
+) -> Result<BigInt, Diagnostic> {
+    match arg_clause {
+        ArgClause::Unnamed(arg_clause) => get_numeric_literal(db, &arg_clause.value(db.upcast())),
+        _ => Err(error_at(
+            db,
+            arg_clause,
+            "Expected unnamed default argument",
+        )),
+    }
+}
+
+/// Parses the second argument of the short string macro, which is the default value.
+/// Returns the default value as a `felt252` literal or a diagnostic error if there was a parsing error.
+pub(crate) fn get_shortstring_default_value(
+    db: &SimpleParserDatabase,
+    arg_clause: &ArgClause,
+    endian: Endian,
+) -> Result<TokenStream, Diagnostic> {
+    let base_expr = match arg_clause {
+        ArgClause::Unnamed(arg_clause) => arg_clause.value(db.upcast()),
+        _ => {
+            return Err(error_at(
+                db,
+                arg_clause,
+                "Expected unnamed default argument",
+            ))
+        }
+    };
+
+    if let Expr::ShortString(base_lit) = &base_expr {
+        let default = base_lit
+            .string_value(db.upcast())
+            .ok_or_else(|| error_at(db, base_lit, "Failed to parse short string default"))?;
+        Ok(TokenStream::new(
+            endian.pack(default.as_bytes()).to_string(),
+        ))
+    } else {
+        Err(error_at(db, &base_expr, "Expected short string default"))
+    }
+}
+
+/// Parses the second argument of the boolean macro, which is the default value.
+/// Returns the default value as a `bool` literal or a diagnostic error if there was a parsing error.
+pub(crate) fn get_bool_default_value(
+    db: &SimpleParserDatabase,
+    arg_clause: &ArgClause,
+) -> Result<TokenStream, Diagnostic> {
+    let base_expr = match arg_clause {
+        ArgClause::Unnamed(arg_clause) => arg_clause.value(db.upcast()),
+        _ => {
+            return Err(error_at(
+                db,
+                arg_clause,
+                "Expected unnamed default argument",
+            ))
+        }
+    };
+
+    match base_expr {
+        Expr::True(_) => Ok(TokenStream::new("true".to_string())),
+        Expr::False(_) => Ok(TokenStream::new("false".to_string())),
+        _ => Err(error_at(db, &base_expr, "Expected boolean default")),
+    }
+}
+
+/// Parses the leading string arguments of the macro, which are the environment variable names to try in order.
+/// Returns the names and the remaining arguments, or a diagnostic error if the first argument is not a name.
+pub(crate) fn get_env_variable_names<'a>(
+    db: &SimpleParserDatabase,
+    args: &'a [Arg],
+) -> Result<(Vec<String>, &'a [Arg]), Diagnostic> {
+    let mut names = vec![get_env_variable_name(db, &args[0].arg_clause(db.upcast()))?];
+    let mut rest = &args[1..];
+    while let Some(arg) = rest.first() {
+        let arg_clause = arg.arg_clause(db.upcast());
+        match &arg_clause {
+            ArgClause::Unnamed(unnamed)
+                if matches!(unnamed.value(db.upcast()), Expr::String(_)) =>
+            {
+                names.push(get_env_variable_name(db, &arg_clause)?);
+                rest = &rest[1..];
+            }
+            _ => break,
+        }
+    }
+    Ok((names, rest))
+}
+
+/// Parses the first argument of the macro, which is the environment variable name.
+/// Returns the environment variable name as a string or a diagnostic error located at the argument if the parsing failed.
+pub(crate) fn get_env_variable_name(
+    db: &SimpleParserDatabase,
+    arg_clause: &ArgClause,
+) -> Result<String, Diagnostic> {
+    let base_expr = match arg_clause {
+        ArgClause::Unnamed(arg_clause) => arg_clause.value(db.upcast()),
+        _ => return Err(error_at(db, arg_clause, "Expected unnamed argument")),
+    };
+
+    if let Expr::String(base_lit) = &base_expr {
+        base_lit
+            .string_value(db.upcast())
+            .ok_or_else(|| error_at(db, &base_expr, "Failed to parse environment variable name"))
+    } else {
+        Err(error_at(
+            db,
+            &base_expr,
+            "Expected environment variable name",
+        ))
+    }
+}
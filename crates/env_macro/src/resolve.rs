@@ -0,0 +1,1006 @@
+// SPDX-License-Identifier: MIT
+// Based on the code from Alexandria library (https://github.com/keep-starknet-strange/alexandria)
+// Copyright (c) 2025 Alexandria Contributors
+
+use std::collections::HashMap;
+use std::env::VarError;
+
+use cairo_lang_macro::{Diagnostic, Diagnostics, TokenStream};
+use cairo_lang_parser::utils::SimpleParserDatabase;
+use cairo_lang_syntax::node::ast::{Arg, ArgClause};
+use cairo_lang_syntax::node::Terminal;
+use cairo_lang_utils::Upcast;
+use num_bigint::BigInt;
+use regex::Regex;
+
+use crate::dotenv;
+use crate::options::{
+    coerce_default_value, get_default_value, get_env_variable_names, parse_env_options, EnvOptions,
+    IntegerType, ValueKind,
+};
+use crate::syntax::{
+    error_at, non_unicode_error, non_unicode_message, not_set_error, parse_macro_args,
+};
+use crate::value::{
+    hex_literal, parse_bool_value, parse_env_value, parse_env_value_as, truncate_value, EnvValue,
+    RADIX_PREFIXES,
+};
+use crate::{dotenv_var, package_path, Env};
+
+/// Process variable holding a prefix prepended to every name looked up by `env!`.
+const GLOBAL_PREFIX_VAR: &str = "CAIRO_ENV_PREFIX";
+
+/// Process variable that, when true, makes `env!` expand every value as a `ByteArray` unless `kind:` is given.
+const STRING_ONLY_VAR: &str = "CAIRO_ENV_STRING_ONLY";
+
+/// Expands the environment variable macro given the macro name, the expected type of the variable and the token stream.
+/// Returns the value of the environment variable as a token stream along with any warnings,
+/// or the diagnostic errors if the variable is not set, there were parsing errors or the value violates constraints.
+/// Variables are resolved through `env`, from the process environment
+/// and then from the `.env` file given as `dotenv:` or the closest one.
+pub(crate) fn expand_env_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<(TokenStream, Vec<Diagnostic>), Diagnostics> {
+    resolve_env_macro("env!", token_stream, env)
+        .map(|expansion| (expansion.token_stream, expansion.warnings))
+}
+
+/// Expands the environment variable macro with a flag telling whether the variable was set.
+/// Returns a `(value, is_set)` tuple expression along with any warnings, or a diagnostic error like `env!`.
+pub(crate) fn expand_env_with_flag_macro(
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<(TokenStream, Vec<Diagnostic>), Diagnostics> {
+    let expansion = resolve_env_macro("env_with_flag!", token_stream, env)?;
+    Ok((
+        TokenStream::new(format!(
+            "({}, {})",
+            expansion.token_stream, expansion.is_set
+        )),
+        expansion.warnings,
+    ))
+}
+
+/// Expansion of a call of `env!` or one of its variants accepting the same arguments.
+struct EnvExpansion {
+    /// The expanded value.
+    token_stream: TokenStream,
+    /// Whether the value was resolved from a variable rather than the default.
+    is_set: bool,
+    /// Warnings to attach to the expansion, e.g. for `warn_on_default:`.
+    warnings: Vec<Diagnostic>,
+}
+
+/// Resolves a call of `env!`, or of the macro `macro_name` accepting the same arguments, see [`expand_env_macro`].
+fn resolve_env_macro(
+    macro_name: &str,
+    token_stream: impl ToString,
+    env: &impl Env,
+) -> Result<EnvExpansion, Diagnostics> {
+    let db = SimpleParserDatabase::default();
+    let macro_args = parse_macro_args(macro_name, token_stream, &db)?;
+    let mut warnings = Vec::new();
+
+    let (env_var_names, option_args) = get_env_variable_names(&db, &macro_args)?;
+
+    // Only a single positional default can follow the variable names.
+    let positional_args: Vec<&Arg> = option_args
+        .iter()
+        .filter(|arg| matches!(arg.arg_clause(db.upcast()), ArgClause::Unnamed(_)))
+        .collect();
+    if positional_args.len() > 1 {
+        return Err(error_at(
+            &db,
+            positional_args[1],
+            format!(
+                "{} accepts at most a name and a default, got {} positional arguments",
+                macro_name,
+                env_var_names.len() + positional_args.len()
+            ),
+        )
+        .into());
+    }
+
+    let options = parse_env_options(&db, env, option_args)?;
+    let name_args = get_name_args(&db, &env_var_names, &macro_args, option_args, &options);
+    // A variable set to a value that is not valid UTF-8 is an error rather than not set, so it never falls back.
+    // The error quotes the argument the name comes from, if any, e.g. not for names referenced by `interpolate:`.
+    let resolve_source =
+        |name: &str, name_arg: Option<&Arg>| -> Result<Option<ResolvedValue>, Diagnostic> {
+            let resolved = |source| move |value| ResolvedValue { value, source };
+            let from_environment = match env.var(name) {
+                Ok(val) => Some(val).map(resolved(ValueSource::Environment)),
+                Err(VarError::NotPresent) => None,
+                Err(VarError::NotUnicode(_)) => {
+                    return Err(match name_arg {
+                        Some(name_arg) => non_unicode_error(&db, name_arg, name),
+                        None => Diagnostic::error(non_unicode_message(name)),
+                    });
+                }
+            };
+            // A malformed `.env` file is only an error if the value would be read from it.
+            let from_dotenv = || -> Result<_, Diagnostic> {
+                let val = match &options.dotenv {
+                    Some(dotenv_vars) => dotenv_vars.get(name).cloned(),
+                    None => dotenv_var(env, name)?,
+                };
+                Ok(val.map(resolved(ValueSource::Dotenv)))
+            };
+            // With `prefer: dotenv`, the `.env` file overrides the process environment.
+            let preferred = match from_environment {
+                Some(_) if options.prefer_dotenv => from_dotenv()?.or(from_environment),
+                Some(_) => from_environment,
+                None => from_dotenv()?,
+            };
+            Ok(preferred.or_else(|| {
+                options
+                    .toml
+                    .then(|| env.manifest_var(name))
+                    .flatten()
+                    .map(resolved(ValueSource::Manifest))
+            }))
+        };
+    // Names are looked up with the global prefix first, then bare.
+    let global_prefix = match env.var(GLOBAL_PREFIX_VAR) {
+        Ok(prefix) => Some(prefix).filter(|prefix| !prefix.is_empty()),
+        Err(VarError::NotPresent) => None,
+        Err(VarError::NotUnicode(_)) => {
+            return Err(Diagnostic::error(non_unicode_message(GLOBAL_PREFIX_VAR)).into());
+        }
+    };
+    let resolve = |name: &str| {
+        let name_arg = name_args.get(name).copied();
+        if let Some(prefix) = &global_prefix {
+            if let Some(resolved) = resolve_source(&format!("{}{}", prefix, name), name_arg)? {
+                return Ok(Some(resolved));
+            }
+        }
+        resolve_source(name, name_arg)
+    };
+    let resolve_value =
+        |name: &str| Ok(resolve(name)?.map(|resolved: ResolvedValue| resolved.value));
+    let string_only = match env.var(STRING_ONLY_VAR) {
+        Ok(val) => parse_bool_value(val.trim()).ok_or_else(|| {
+            Diagnostic::error(format!(
+                "Failed to parse boolean value of environment variable {}: {}",
+                STRING_ONLY_VAR,
+                truncate_value(&val)
+            ))
+        })?,
+        Err(VarError::NotPresent) => false,
+        Err(VarError::NotUnicode(_)) => {
+            return Err(Diagnostic::error(non_unicode_message(STRING_ONLY_VAR)).into());
+        }
+    };
+
+    if options.join.is_some() {
+        return join_env_values(
+            macro_name,
+            &db,
+            env,
+            &macro_args,
+            &env_var_names,
+            &options,
+            &resolve_value,
+        );
+    }
+
+    let mut env_var_names = get_lookup_names(env_var_names, &options);
+    // The fallback variable is only looked up after all the requested names.
+    if let Some(fallback_var) = &options.fallback_var {
+        env_var_names.push(fallback_var.clone());
+    }
+
+    // The first variable that is set wins, empty and null values count as not set, see [`EnvOptions::counts_as_set`].
+    // The value is taken entirely from the first source that defines it, so only that value drives type detection.
+    let mut resolved = None;
+    for name in &env_var_names {
+        if let Some(value) =
+            resolve(name)?.filter(|resolved| options.counts_as_set(&resolved.value))
+        {
+            resolved = Some((name, value));
+            break;
+        }
+    }
+    let is_set = resolved.is_some();
+    let token_stream = match resolved {
+        Some((env_var_name, resolved)) => {
+            let source = resolved.source;
+            let token_stream = expand_resolved_value(
+                env,
+                env_var_name,
+                resolved.value,
+                &options,
+                string_only,
+                &resolve_value,
+            )
+            .map_err(|errors| {
+                errors
+                    .into_iter()
+                    .map(|err| source.annotate(env_var_name, err))
+                    .collect::<Diagnostics>()
+            })?;
+            if options.debug {
+                log_resolution(macro_name, env_var_name, source.describe(), &token_stream);
+            }
+            token_stream
+        }
+        None => {
+            if let Some(default_expr) = &options.default {
+                if options.warn_on_default {
+                    warnings.push(Diagnostic::warn(format!(
+                        "Using default for {}; variable not set",
+                        env_var_names.join(", ")
+                    )));
+                }
+                let env_value = get_default_value(&db, default_expr)?;
+                let env_value = coerce_default_value(
+                    &env_var_names[0],
+                    env_value,
+                    options.kind,
+                    options.strict_default,
+                )?;
+                let token_stream = expand_env_value(&env_var_names[0], env_value, &options)?;
+                if options.debug {
+                    log_resolution(macro_name, &env_var_names[0], "the default", &token_stream);
+                }
+                token_stream
+            } else if let Some(message) = &options.message {
+                return Err(error_at(&db, &macro_args[0], message).into());
+            } else if let [env_var_name] = env_var_names.as_slice() {
+                return Err(not_set_error(
+                    &db,
+                    &macro_args[0],
+                    env_var_name,
+                    Some((macro_name, "0")),
+                )
+                .into());
+            } else {
+                return Err(error_at(
+                    &db,
+                    &macro_args[0],
+                    format!(
+                        "None of the environment variables {} are set",
+                        env_var_names.join(", ")
+                    ),
+                )
+                .into());
+            }
+        }
+    };
+    Ok(EnvExpansion {
+        token_stream,
+        is_set,
+        warnings,
+    })
+}
+
+/// Logs the expansion of a variable and where its value came from to stderr, for `debug: true`.
+fn log_resolution(macro_name: &str, env_var_name: &str, source: &str, token_stream: &TokenStream) {
+    eprintln!(
+        "{}",
+        resolution_log_line(macro_name, env_var_name, source, token_stream)
+    );
+}
+
+/// Returns the line logged for the expansion of a variable.
+/// The line has the form `env!: PORT = 8080 (from .env)`, starting with the name of the macro called.
+pub(crate) fn resolution_log_line(
+    macro_name: &str,
+    env_var_name: &str,
+    source: &str,
+    token_stream: &TokenStream,
+) -> String {
+    format!(
+        "{}: {} = {} (from {})",
+        macro_name, env_var_name, token_stream, source
+    )
+}
+
+/// Source a variable value was resolved from, in the order of precedence.
+#[derive(Clone, Copy)]
+enum ValueSource {
+    /// The process environment.
+    Environment,
+    /// A `.env` file, either given as `dotenv:` or the closest one.
+    Dotenv,
+    /// The `[tool.env]` table of `Scarb.toml`, with `toml: true`.
+    Manifest,
+}
+
+impl ValueSource {
+    /// Returns the description of the source used in diagnostics.
+    fn describe(self) -> &'static str {
+        match self {
+            ValueSource::Environment => "the environment",
+            ValueSource::Dotenv => ".env",
+            ValueSource::Manifest => "Scarb.toml",
+        }
+    }
+
+    /// Appends a note saying where the value of the variable came from to a diagnostic about it.
+    fn annotate(self, env_var_name: &str, mut diagnostic: Diagnostic) -> Diagnostic {
+        diagnostic.message = format!(
+            "{}\nnote: {} was read from {}",
+            diagnostic.message,
+            env_var_name,
+            self.describe()
+        );
+        diagnostic
+    }
+}
+
+/// A variable value along with the source it was resolved from.
+struct ResolvedValue {
+    value: String,
+    source: ValueSource,
+}
+
+/// Applies the value options, like `from_file:`, `interpolate:` and `kind:`, to a resolved variable value.
+/// With `string_only`, values are never parsed as numbers unless `kind:` is given.
+/// Returns the value as a token stream or the diagnostic errors if it fails to parse or violates constraints.
+fn expand_resolved_value(
+    env: &impl Env,
+    env_var_name: &str,
+    val: String,
+    options: &EnvOptions,
+    string_only: bool,
+    resolve: &impl Fn(&str) -> Result<Option<String>, Diagnostic>,
+) -> Result<TokenStream, Diagnostics> {
+    let val = if options.from_file {
+        read_value_file(env, env_var_name, &val)?
+    } else {
+        val
+    };
+    let val = if options.interpolate {
+        interpolate_env_value(
+            env_var_name,
+            &val,
+            resolve,
+            &mut vec![env_var_name.to_string()],
+        )?
+    } else {
+        val
+    };
+    let val = if options.trim { val.trim() } else { &val };
+    let val = if options.strip_quotes {
+        strip_matching_quotes(val)
+    } else {
+        val
+    };
+    // Allowed values are compared against the raw string, so it is never parsed as a number.
+    // Empty values are only resolved with `allow_empty:` and expand to an empty string,
+    // unless `kind:` requests another type, which they are not a valid value of.
+    let max_digits = options.max_digits.unwrap_or(DEFAULT_MAX_DIGITS);
+    let env_value = if options.one_of.is_some() || options.matches.is_some() {
+        EnvValue::String(val.to_string())
+    } else if let Some(kind) = options.kind {
+        if matches!(kind, ValueKind::Felt | ValueKind::Hex) {
+            check_numeric_length(env_var_name, val, max_digits)?;
+        }
+        parse_env_value_as(env_var_name, val, kind)?
+    } else if string_only || val.trim().is_empty() {
+        EnvValue::String(val.to_string())
+    } else {
+        check_numeric_length(env_var_name, val, max_digits)?;
+        parse_env_value(env_var_name, val)?
+    };
+    expand_env_value(env_var_name, env_value, options)
+}
+
+/// Removes a single matching pair of single or double quotes surrounding a value, like in `"bob"`.
+/// Values with mismatched or no quotes are returned as is.
+fn strip_matching_quotes(val: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|&quote| val.strip_prefix(quote)?.strip_suffix(quote))
+        .unwrap_or(val)
+}
+
+/// Maximum length of a numeric value in characters, unless another one is given as `max_digits:`.
+/// This is well beyond the longest decimal or hexadecimal `u256`, while keeping huge values from being parsed.
+const DEFAULT_MAX_DIGITS: usize = 100;
+
+/// Checks that a value that looks numeric is not longer than `max_digits` characters, ignoring surrounding whitespace.
+/// Returns a diagnostic error before the value is parsed as a number if it is. Other values are not checked.
+fn check_numeric_length(
+    env_var_name: &str,
+    val: &str,
+    max_digits: usize,
+) -> Result<(), Diagnostic> {
+    let val = val.trim();
+    if val.len() <= max_digits || !looks_numeric(val) {
+        return Ok(());
+    }
+    Err(Diagnostic::error(format!(
+        "{} is a numeric value of {} characters, longer than max_digits {}",
+        env_var_name,
+        val.len(),
+        max_digits
+    )))
+}
+
+/// Checks whether a value looks like a number: an optional sign and a prefixed number, like `0xff`,
+/// or a decimal number, possibly with digit separators or in scientific notation.
+fn looks_numeric(val: &str) -> bool {
+    let digits = val.strip_prefix(['-', '+']).unwrap_or(val);
+    let bytes = digits.as_bytes();
+    let prefixed = RADIX_PREFIXES.iter().any(|(prefix, _)| {
+        bytes.len() >= prefix.len() && bytes[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+    });
+    if prefixed {
+        return bytes[2..]
+            .iter()
+            .all(|b| b.is_ascii_hexdigit() || *b == b'_');
+    }
+    bytes.first().is_some_and(u8::is_ascii_digit)
+        && bytes
+            .iter()
+            .all(|b| b.is_ascii_digit() || matches!(b, b'_' | b'.' | b'e' | b'E' | b'+' | b'-'))
+}
+
+/// Checks the value, either resolved or the default, against the constraints given in the macro options.
+/// Returns the value as a token stream or the diagnostic errors of all the violated constraints.
+fn expand_env_value(
+    env_var_name: &str,
+    env_value: EnvValue,
+    options: &EnvOptions,
+) -> Result<TokenStream, Diagnostics> {
+    // Every constraint is checked, even after one fails, so every violation is reported at once.
+    let mut errors = Vec::new();
+    let env_value = match &options.one_of {
+        Some(allowed) => apply_check(&mut errors, env_value, |env_value| {
+            check_one_of(env_var_name, env_value, allowed, options.case_insensitive)
+        }),
+        None => env_value,
+    };
+    let env_value = match &options.matches {
+        Some(pattern) => apply_check(&mut errors, env_value, |env_value| {
+            check_matches(env_var_name, env_value, pattern)
+        }),
+        None => env_value,
+    };
+    let env_value = apply_check(&mut errors, env_value, |env_value| {
+        check_field_range(
+            env_var_name,
+            env_value,
+            options.prime.as_ref(),
+            options.wrapping,
+        )
+    });
+    errors.extend(check_env_value(env_var_name, &env_value, options));
+    if let Some(integer_type) = options.integer_type {
+        errors.extend(check_integer_type(env_var_name, &env_value, integer_type).err());
+    }
+    if let Some(suffix) = options.suffix {
+        errors.extend(check_integer_type(env_var_name, &env_value, suffix).err());
+    }
+    let env_value = match options.transform {
+        Some(transform) => apply_check(&mut errors, env_value, |env_value| {
+            transform.apply(env_var_name, env_value)
+        }),
+        None => env_value,
+    };
+    errors.extend(check_string_length(env_var_name, &env_value, options).err());
+    let literal = match (options.kind, &env_value) {
+        (Some(ValueKind::Hex), EnvValue::Numeric(numeric_val)) => {
+            hex_literal(env_var_name, numeric_val, options.width)
+                .map_err(|err| errors.push(err))
+                .unwrap_or_default()
+        }
+        _ => env_value.to_literal(),
+    };
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+    // Expression defaults are emitted verbatim, as a suffix can only be appended to a literal.
+    match (options.suffix, &env_value) {
+        (Some(suffix), EnvValue::Numeric(_)) => {
+            Ok(TokenStream::new(format!("{}_{}", literal, suffix.name)))
+        }
+        _ => Ok(TokenStream::new(literal)),
+    }
+}
+
+/// Applies a check that may convert the value, like `one_of:` returning the canonical value.
+/// If the check fails, its error is collected and the value is returned unchanged, so the remaining checks still run.
+fn apply_check(
+    errors: &mut Vec<Diagnostic>,
+    env_value: EnvValue,
+    check: impl FnOnce(EnvValue) -> Result<EnvValue, Diagnostic>,
+) -> EnvValue {
+    match check(env_value.clone()) {
+        Ok(checked_value) => checked_value,
+        Err(err) => {
+            errors.push(err);
+            env_value
+        }
+    }
+}
+
+/// Resolves all the variables as strings and joins them with the separator given as `join:`.
+/// Returns the joined value as a `ByteArray` literal, the default if some variables are not set,
+/// or a diagnostic error listing the variables that are not set if there is no default.
+/// Every part is checked, even after one fails, so the violations of all the parts are reported at once.
+/// The value is returned along with whether all the variables were set.
+/// `macro_name` is the name of the macro called, used in the help of diagnostics.
+fn join_env_values(
+    macro_name: &str,
+    db: &SimpleParserDatabase,
+    env: &impl Env,
+    macro_args: &[Arg],
+    env_var_names: &[String],
+    options: &EnvOptions,
+    resolve: &impl Fn(&str) -> Result<Option<String>, Diagnostic>,
+) -> Result<EnvExpansion, Diagnostics> {
+    let separator = options.join.as_deref().unwrap_or_default();
+    let mut values = Vec::with_capacity(env_var_names.len());
+    let mut missing = Vec::new();
+    let mut errors = Vec::new();
+    for (name_arg, env_var_name) in macro_args.iter().zip(env_var_names) {
+        let Some(val) = resolve(env_var_name)?.filter(|val| options.counts_as_set(val)) else {
+            missing.push((name_arg, env_var_name));
+            continue;
+        };
+        let val = if options.from_file {
+            read_value_file(env, env_var_name, &val)?
+        } else {
+            val
+        };
+        let val = if options.interpolate {
+            interpolate_env_value(env_var_name, &val, resolve, &mut vec![env_var_name.clone()])?
+        } else {
+            val
+        };
+        let val = if options.trim { val.trim() } else { &val };
+        let val = if options.strip_quotes {
+            strip_matching_quotes(val)
+        } else {
+            val
+        };
+        values.push(check_join_part(&mut errors, env_var_name, val, options));
+    }
+    if !errors.is_empty() {
+        return Err(errors.into());
+    }
+
+    if missing.is_empty() {
+        let joined = EnvValue::String(values.join(separator));
+        check_string_length(&env_var_names.join(", "), &joined, options)?;
+        return Ok(EnvExpansion {
+            token_stream: TokenStream::new(joined.to_literal()),
+            is_set: true,
+            warnings: Vec::new(),
+        });
+    }
+
+    let missing_names: Vec<&str> = missing.iter().map(|(_, name)| name.as_str()).collect();
+    if let Some(default_expr) = &options.default {
+        let mut warnings = Vec::new();
+        if options.warn_on_default {
+            warnings.push(Diagnostic::warn(format!(
+                "Using default for {}; variable not set",
+                missing_names.join(", ")
+            )));
+        }
+        // The joined value is always a string, so a numeric or boolean default is expanded as one too.
+        let env_value = coerce_default_value(
+            &env_var_names[0],
+            get_default_value(db, default_expr)?,
+            Some(ValueKind::ByteArray),
+            options.strict_default,
+        )?;
+        return Ok(EnvExpansion {
+            token_stream: TokenStream::new(env_value.to_literal()),
+            is_set: false,
+            warnings,
+        });
+    }
+    if let Some(message) = &options.message {
+        return Err(error_at(db, &macro_args[0], message).into());
+    }
+    match missing.as_slice() {
+        [(name_arg, env_var_name)] => Err(not_set_error(
+            db,
+            name_arg,
+            env_var_name,
+            Some((macro_name, "default: \"\"")),
+        )
+        .into()),
+        _ => Err(error_at(
+            db,
+            &macro_args[0],
+            format!("Environment variables {} not set", missing_names.join(", ")),
+        )
+        .into()),
+    }
+}
+
+/// Checks a single part of a joined value against `one_of:` and `matches:` and applies `transform:`.
+/// Returns the part as a string, collecting a diagnostic error for each violated constraint.
+fn check_join_part(
+    errors: &mut Vec<Diagnostic>,
+    env_var_name: &str,
+    val: &str,
+    options: &EnvOptions,
+) -> String {
+    let mut env_value = EnvValue::String(val.to_string());
+    if let Some(allowed) = &options.one_of {
+        env_value = apply_check(errors, env_value, |env_value| {
+            check_one_of(env_var_name, env_value, allowed, options.case_insensitive)
+        });
+    }
+    if let Some(pattern) = &options.matches {
+        env_value = apply_check(errors, env_value, |env_value| {
+            check_matches(env_var_name, env_value, pattern)
+        });
+    }
+    if let Some(transform) = options.transform {
+        env_value = apply_check(errors, env_value, |env_value| {
+            transform.apply(env_var_name, env_value)
+        });
+    }
+    match env_value {
+        EnvValue::String(string_val) => string_val,
+        _ => val.to_string(),
+    }
+}
+
+/// Reads the file named by the value of an environment variable, like `API_KEY_FILE=/run/secrets/api_key`.
+/// A relative path is resolved against the package directory, like the one given as `dotenv:`.
+/// Returns the trimmed contents of the file, without a byte order mark and with `\n` line endings,
+/// or a diagnostic error including the path if it can't be read.
+fn read_value_file(env: &impl Env, env_var_name: &str, path: &str) -> Result<String, Diagnostic> {
+    let path = package_path(env, path.trim());
+    std::fs::read_to_string(&path)
+        .map(|contents| dotenv::normalize_text(&contents).trim().to_string())
+        .map_err(|err| {
+            Diagnostic::error(format!(
+                "Failed to read file {} named by environment variable {}: {}",
+                path.display(),
+                env_var_name,
+                err
+            ))
+        })
+}
+
+/// Maximum nesting of `${VAR}` references, so that cyclic references can't recurse forever.
+const MAX_INTERPOLATION_DEPTH: usize = 16;
+
+/// Expands the `${VAR}` references in the value of an environment variable, recursively.
+/// A literal dollar sign is written as `$$` or `\$`, e.g. `PRICE=$$5` expands to `$5`.
+/// `stack` holds the variables being expanded, starting with the one the value belongs to.
+/// Returns the expanded value or a diagnostic error if a reference is not set, is unterminated or is cyclic.
+fn interpolate_env_value(
+    env_var_name: &str,
+    val: &str,
+    resolve: &impl Fn(&str) -> Result<Option<String>, Diagnostic>,
+    stack: &mut Vec<String>,
+) -> Result<String, Diagnostic> {
+    let mut interpolated = String::with_capacity(val.len());
+    let mut rest = val;
+    while let Some(start) = rest.find(['$', '\\']) {
+        interpolated.push_str(&rest[..start]);
+        let escape = &rest[start..];
+        if escape.starts_with("$$") || escape.starts_with("\\$") {
+            interpolated.push('$');
+            rest = &escape[2..];
+            continue;
+        }
+        if !escape.starts_with("${") {
+            // A lone `$` or backslash is kept as is.
+            interpolated.push_str(&escape[..1]);
+            rest = &escape[1..];
+            continue;
+        }
+        let (name, after) = escape[2..].split_once('}').ok_or_else(|| {
+            Diagnostic::error(format!(
+                "Unterminated reference in environment variable {}: {}",
+                env_var_name,
+                truncate_value(val)
+            ))
+        })?;
+        if stack.iter().any(|visited| visited == name) {
+            return Err(Diagnostic::error(format!(
+                "Cyclic reference in environment variable {}: {} -> {}",
+                env_var_name,
+                stack.join(" -> "),
+                name
+            )));
+        }
+        if stack.len() >= MAX_INTERPOLATION_DEPTH {
+            return Err(Diagnostic::error(format!(
+                "References in environment variable {} are nested deeper than {} levels",
+                env_var_name, MAX_INTERPOLATION_DEPTH
+            )));
+        }
+        let referenced = resolve(name)?.ok_or_else(|| {
+            Diagnostic::error(format!(
+                "Environment variable {} referenced by {} is not set",
+                name,
+                stack.last().map_or(env_var_name, String::as_str)
+            ))
+        })?;
+        stack.push(name.to_string());
+        interpolated.push_str(&interpolate_env_value(
+            env_var_name,
+            &referenced,
+            resolve,
+            stack,
+        )?);
+        stack.pop();
+        rest = after;
+    }
+    interpolated.push_str(rest);
+    Ok(interpolated)
+}
+
+/// Returns the names to look up in order, each followed by its unprefixed form if `strip_prefix:` is given.
+fn get_lookup_names(env_var_names: Vec<String>, options: &EnvOptions) -> Vec<String> {
+    let Some(prefix) = options
+        .strip_prefix
+        .as_deref()
+        .filter(|prefix| !prefix.is_empty())
+    else {
+        return env_var_names;
+    };
+
+    let mut lookup_names = Vec::with_capacity(env_var_names.len() * 2);
+    for name in env_var_names {
+        let stripped_name = name
+            .strip_prefix(prefix)
+            .filter(|stripped_name| !stripped_name.is_empty())
+            .map(str::to_string);
+        lookup_names.push(name);
+        lookup_names.extend(stripped_name);
+    }
+    lookup_names
+}
+
+/// Maps each name to look up to the argument it comes from, so diagnostics about its value quote that argument.
+/// Names stripped by `strip_prefix:` map to the argument of the full name, and the `fallback_var:` name to that option.
+fn get_name_args<'a>(
+    db: &SimpleParserDatabase,
+    env_var_names: &[String],
+    macro_args: &'a [Arg],
+    option_args: &'a [Arg],
+    options: &EnvOptions,
+) -> HashMap<String, &'a Arg> {
+    let mut name_args = HashMap::new();
+    for (name, arg) in env_var_names.iter().zip(macro_args) {
+        for lookup_name in get_lookup_names(vec![name.clone()], options) {
+            name_args.entry(lookup_name).or_insert(arg);
+        }
+    }
+    if let Some(fallback_var) = &options.fallback_var {
+        let fallback_arg = option_args
+            .iter()
+            .find(|arg| match arg.arg_clause(db.upcast()) {
+                ArgClause::Named(arg_clause) => {
+                    arg_clause.name(db.upcast()).text(db.upcast()) == "fallback_var"
+                }
+                _ => false,
+            });
+        if let Some(fallback_arg) = fallback_arg {
+            name_args
+                .entry(fallback_var.clone())
+                .or_insert(fallback_arg);
+        }
+    }
+    name_args
+}
+
+/// Checks that the resolved value is one of the allowed values, ignoring case if `case_insensitive` is set.
+/// Returns the first matching allowed value as a string or a diagnostic error listing the allowed values.
+fn check_one_of(
+    env_var_name: &str,
+    env_value: EnvValue,
+    allowed: &[String],
+    case_insensitive: bool,
+) -> Result<EnvValue, Diagnostic> {
+    let string_val = match env_value {
+        EnvValue::Numeric(numeric_val) => numeric_val.to_string(),
+        EnvValue::String(string_val) => string_val,
+        EnvValue::Bool(bool_val) => bool_val.to_string(),
+        // Expressions can't be evaluated at expansion time.
+        EnvValue::Expr(_) => return Ok(env_value),
+    };
+    let canonical = if case_insensitive {
+        let lowercase_val = string_val.to_lowercase();
+        allowed
+            .iter()
+            .find(|allowed_val| allowed_val.to_lowercase() == lowercase_val)
+    } else {
+        allowed
+            .iter()
+            .find(|allowed_val| **allowed_val == string_val)
+    };
+    if let Some(canonical) = canonical {
+        Ok(EnvValue::String(canonical.clone()))
+    } else {
+        Err(Diagnostic::error(format!(
+            "{}={} is not one of the allowed values: {}",
+            env_var_name,
+            string_val,
+            allowed.join(", ")
+        )))
+    }
+}
+
+/// Checks that the resolved value matches the pattern given as `matches:`.
+/// Returns the value as a string or a diagnostic error naming the pattern.
+fn check_matches(
+    env_var_name: &str,
+    env_value: EnvValue,
+    pattern: &Regex,
+) -> Result<EnvValue, Diagnostic> {
+    let string_val = match env_value {
+        EnvValue::Numeric(numeric_val) => numeric_val.to_string(),
+        EnvValue::String(string_val) => string_val,
+        EnvValue::Bool(bool_val) => bool_val.to_string(),
+        // Expressions can't be evaluated at expansion time.
+        EnvValue::Expr(_) => return Ok(env_value),
+    };
+    if pattern.is_match(&string_val) {
+        Ok(EnvValue::String(string_val))
+    } else {
+        Err(Diagnostic::error(format!(
+            "{}={} does not match the pattern {}",
+            env_var_name,
+            truncate_value(&string_val),
+            pattern
+        )))
+    }
+}
+
+/// Returns the STARK field prime `2^251 + 17 * 2^192 + 1`.
+pub(crate) fn stark_prime() -> BigInt {
+    (BigInt::from(1) << 251) + (BigInt::from(17) << 192) + 1
+}
+
+/// Checks that a numeric value fits into a `felt252`, i.e. its absolute value is below the field prime.
+/// If `wrapping` is set, an out of range value is reduced modulo the prime instead of producing a diagnostic error.
+pub(crate) fn check_felt_range(
+    env_var_name: &str,
+    env_value: EnvValue,
+    wrapping: bool,
+) -> Result<EnvValue, Diagnostic> {
+    check_field_range(env_var_name, env_value, None, wrapping)
+}
+
+/// Checks that a numeric value fits into the field of the given prime, or the STARK field if it is `None`.
+/// If `wrapping` is set, an out of range value is reduced modulo the prime instead of producing a diagnostic error.
+fn check_field_range(
+    env_var_name: &str,
+    env_value: EnvValue,
+    prime: Option<&BigInt>,
+    wrapping: bool,
+) -> Result<EnvValue, Diagnostic> {
+    let EnvValue::Numeric(numeric_val) = env_value else {
+        return Ok(env_value);
+    };
+
+    let (prime, field) = match prime {
+        Some(prime) => (prime.clone(), format!("the field of prime {}", prime)),
+        None => (stark_prime(), "felt252".to_string()),
+    };
+    if numeric_val < prime && numeric_val > -&prime {
+        return Ok(EnvValue::Numeric(numeric_val));
+    }
+    if wrapping {
+        return Ok(EnvValue::Numeric(
+            ((numeric_val % &prime) + &prime) % &prime,
+        ));
+    }
+    Err(Diagnostic::error(format!(
+        "{}={} does not fit into {}, pass wrapping: true to reduce it modulo the field prime",
+        env_var_name, numeric_val, field
+    )))
+}
+
+/// Checks the resolved value against the constraints given in the macro options.
+/// Returns a diagnostic error naming the variable for each violated constraint.
+fn check_env_value(
+    env_var_name: &str,
+    env_value: &EnvValue,
+    options: &EnvOptions,
+) -> Vec<Diagnostic> {
+    if options.min.is_none() && options.max.is_none() && !options.nonzero {
+        return Vec::new();
+    }
+
+    let numeric_val = match env_value {
+        EnvValue::Numeric(numeric_val) => numeric_val,
+        // Expressions can't be evaluated at expansion time.
+        EnvValue::Expr(_) => return Vec::new(),
+        EnvValue::String(_) | EnvValue::Bool(_) => {
+            return vec![Diagnostic::error(format!(
+                "{}={} is not numeric, min, max and nonzero require a numeric value",
+                env_var_name,
+                env_value.to_literal()
+            ))]
+        }
+    };
+
+    let mut errors = Vec::new();
+    if options.nonzero && *numeric_val == BigInt::ZERO {
+        errors.push(Diagnostic::error(format!(
+            "{}=0 must be nonzero",
+            env_var_name
+        )));
+    }
+    // Both bounds are inclusive.
+    if let Some(min) = options.min.as_ref().filter(|min| numeric_val < *min) {
+        errors.push(Diagnostic::error(format!(
+            "{}={} is below min {}",
+            env_var_name, numeric_val, min
+        )));
+    }
+    if let Some(max) = options.max.as_ref().filter(|max| numeric_val > *max) {
+        errors.push(Diagnostic::error(format!(
+            "{}={} exceeds max {}",
+            env_var_name, numeric_val, max
+        )));
+    }
+    errors
+}
+
+/// Checks that the length of a string value in UTF-8 bytes is within the bounds given as `min_len:` and `max_len:`.
+/// Returns a diagnostic error reporting the actual length if it is not. Other values are not checked.
+fn check_string_length(
+    env_var_name: &str,
+    env_value: &EnvValue,
+    options: &EnvOptions,
+) -> Result<(), Diagnostic> {
+    let EnvValue::String(string_val) = env_value else {
+        return Ok(());
+    };
+
+    // Both bounds are inclusive.
+    let len = string_val.len();
+    if let Some(min_len) = options.min_len.filter(|min_len| len < *min_len) {
+        return Err(Diagnostic::error(format!(
+            "{} is {} bytes long, shorter than min_len {}",
+            env_var_name, len, min_len
+        )));
+    }
+    if let Some(max_len) = options.max_len.filter(|max_len| len > *max_len) {
+        return Err(Diagnostic::error(format!(
+            "{} is {} bytes long, longer than max_len {}",
+            env_var_name, len, max_len
+        )));
+    }
+    Ok(())
+}
+
+/// Checks that the resolved value is within the range of the integer type given as `signed:` or `unsigned:`.
+/// Returns a diagnostic error naming the variable and the range if it is not.
+fn check_integer_type(
+    env_var_name: &str,
+    env_value: &EnvValue,
+    integer_type: IntegerType,
+) -> Result<(), Diagnostic> {
+    let numeric_val = match env_value {
+        EnvValue::Numeric(numeric_val) => numeric_val,
+        // Expressions can't be evaluated at expansion time.
+        EnvValue::Expr(_) => return Ok(()),
+        EnvValue::String(_) | EnvValue::Bool(_) => {
+            return Err(Diagnostic::error(format!(
+                "{}={} is not numeric, {} requires a numeric value",
+                env_var_name,
+                env_value.to_literal(),
+                integer_type.name
+            )))
+        }
+    };
+
+    let (min, max) = (integer_type.min(), integer_type.max());
+    if *numeric_val < min || *numeric_val > max {
+        return Err(Diagnostic::error(format!(
+            "{}={} is out of range for {} ({}..={})",
+            env_var_name, numeric_val, integer_type.name, min, max
+        )));
+    }
+    Ok(())
+}
@@ -0,0 +1,253 @@
+// SPDX-License-Identifier: MIT
+// Based on the code from Alexandria library (https://github.com/keep-starknet-strange/alexandria)
+// Copyright (c) 2025 Alexandria Contributors
+
+use cairo_lang_filesystem::ids::{FileId, FileKind, FileLongId, VirtualFile};
+use cairo_lang_macro::Diagnostic;
+use cairo_lang_parser::db::ParserGroup;
+use cairo_lang_parser::utils::SimpleParserDatabase;
+use cairo_lang_syntax::node::ast::{
+    Arg, ArgClause, Expr, ExprInlineMacro, ModuleItem, WrappedArgList,
+};
+use cairo_lang_syntax::node::TypedSyntaxNode;
+use cairo_lang_utils::{Intern, Upcast};
+
+use crate::options::get_env_variable_name;
+use crate::value::string_literal;
+
+/// Parses the macro call and returns its arguments.
+/// Returns a diagnostic error if no arguments were given, since the first one is always the environment variable name.
+pub(crate) fn parse_macro_args(
+    macro_name: &str,
+    token_stream: impl ToString,
+    db: &SimpleParserDatabase,
+) -> Result<Vec<Arg>, Diagnostic> {
+    let macro_args = parse_macro_arg_list(macro_name, token_stream, db)?;
+    if macro_args.is_empty() {
+        return Err(Diagnostic::error(
+            "Please specify the environment variable name",
+        ));
+    }
+
+    Ok(macro_args)
+}
+
+/// Parses the arguments of the macro like [`parse_macro_args`], but also accepts a call without arguments.
+pub(crate) fn parse_macro_arg_list(
+    macro_name: &str,
+    token_stream: impl ToString,
+    db: &SimpleParserDatabase,
+) -> Result<Vec<Arg>, Diagnostic> {
+    // Get the ExprInlineMacro object so we can use the helper functions.
+    let mac = parse_inline_macro(macro_name, token_stream, db)?;
+    let macro_args = inline_macro_args(db, &mac);
+    // A trailing comma, like `env!("X", 1,)`, must not count as an argument, so placeholder arguments
+    // the parser may recover with are dropped before the arguments are counted and indexed.
+    Ok(macro_args
+        .into_iter()
+        .filter(|arg| !is_missing_arg(db, arg))
+        .collect())
+}
+
+/// Returns the arguments of the macro call, which can be wrapped in parentheses, brackets or braces.
+fn inline_macro_args(db: &SimpleParserDatabase, mac: &ExprInlineMacro) -> Vec<Arg> {
+    match mac.arguments(db.upcast()) {
+        WrappedArgList::ParenthesizedArgList(args) => {
+            args.arguments(db.upcast()).elements(db.upcast())
+        }
+        WrappedArgList::BracketedArgList(args) => args.arguments(db.upcast()).elements(db.upcast()),
+        WrappedArgList::BracedArgList(args) => args.arguments(db.upcast()).elements(db.upcast()),
+        WrappedArgList::Missing(_) => vec![],
+    }
+}
+
+/// Checks whether an argument is an empty placeholder, i.e. an unnamed argument without an expression.
+fn is_missing_arg(db: &SimpleParserDatabase, arg: &Arg) -> bool {
+    match arg.arg_clause(db.upcast()) {
+        ArgClause::Unnamed(arg_clause) => matches!(arg_clause.value(db.upcast()), Expr::Missing(_)),
+        _ => false,
+    }
+}
+
+/// Returns an [`ExprInlineMacro`] from the text received.
+/// The expected text is the macro arguments.
+/// Returns a diagnostic error if the text can't be parsed as a call of the macro.
+fn parse_inline_macro(
+    macro_name: &str,
+    token_stream: impl ToString,
+    db: &SimpleParserDatabase,
+) -> Result<ExprInlineMacro, Diagnostic> {
+    // Create a virtual file that will be parsed, named after the macro until the call it holds is parsed.
+    let content = format!("{}{}", macro_name, token_stream.to_string());
+    let file = virtual_expr_file(db, macro_name, &content);
+
+    // The file has already been parsed once to reach this macro, so this should only fail on malformed input.
+    let node = db
+        .file_expr_syntax(file)
+        .map_err(|_| Diagnostic::error(format!("Could not parse {} arguments", macro_name)))?;
+    let file_name = virtual_file_name(db, macro_name, &node);
+    let parse_error = || Diagnostic::error(format!("Could not parse {} arguments", file_name));
+    // The parser recovers from syntax errors, like a missing comma, so they are only reported as diagnostics.
+    // They are taken from the file named after the call, so that they refer to it.
+    if !db.file_syntax_diagnostics(file).is_empty() {
+        let syntax_diagnostics =
+            db.file_syntax_diagnostics(virtual_expr_file(db, &file_name, &content));
+        let mut diagnostic = parse_error();
+        diagnostic.message = format!(
+            "{}\n{}",
+            diagnostic.message,
+            syntax_diagnostics.format(db).trim_end()
+        );
+        return Err(diagnostic);
+    }
+
+    let Expr::InlineMacro(inline_macro) = node else {
+        return Err(parse_error());
+    };
+
+    Ok(inline_macro)
+}
+
+/// Parses the arguments of an attribute macro, like `("MYAPP_")` in `#[env_consts("MYAPP_")]`.
+/// Returns no arguments if the attribute has none, or a diagnostic error if they can't be parsed.
+pub(crate) fn parse_attribute_args(
+    macro_name: &str,
+    attr: impl ToString,
+    db: &SimpleParserDatabase,
+) -> Result<Vec<Arg>, Diagnostic> {
+    let attr = attr.to_string();
+    let attr = attr.trim();
+    if attr.is_empty() {
+        return Ok(vec![]);
+    }
+    if attr.starts_with('(') {
+        parse_macro_arg_list(macro_name, attr, db)
+    } else {
+        parse_macro_arg_list(macro_name, format!("({})", attr), db)
+    }
+}
+
+/// Parses the item an attribute macro is applied to.
+/// Returns the item or a diagnostic error if the text is not a single well-formed item.
+pub(crate) fn parse_module_item(
+    macro_name: &str,
+    item: &str,
+    db: &SimpleParserDatabase,
+) -> Result<ModuleItem, Diagnostic> {
+    let file = FileLongId::Virtual(VirtualFile {
+        parent: None,
+        name: macro_name.into(),
+        content: item.into(),
+        code_mappings: [].into(),
+        kind: FileKind::Module,
+    })
+    .intern(db);
+
+    let parse_error = || {
+        Diagnostic::error(format!(
+            "Could not parse the item {} is applied to",
+            macro_name
+        ))
+    };
+    let syntax_file = db.file_module_syntax(file).map_err(|_| parse_error())?;
+    if !db.file_syntax_diagnostics(file).is_empty() {
+        return Err(parse_error());
+    }
+    match syntax_file
+        .items(db.upcast())
+        .elements(db.upcast())
+        .as_slice()
+    {
+        [item] => Ok(item.clone()),
+        _ => Err(parse_error()),
+    }
+}
+
+/// Returns the text of a syntax node without the surrounding whitespace and comments.
+fn syntax_text(db: &SimpleParserDatabase, node: &impl TypedSyntaxNode) -> String {
+    node.as_syntax_node().get_text_without_trivia(db.upcast())
+}
+
+/// Interns a virtual file holding the macro call `content`, to be parsed as an expression.
+fn virtual_expr_file(db: &SimpleParserDatabase, name: &str, content: &str) -> FileId {
+    FileLongId::Virtual(VirtualFile {
+        parent: None,
+        name: name.into(),
+        content: content.into(),
+        code_mappings: [].into(),
+        kind: FileKind::Expr,
+    })
+    .intern(db)
+}
+
+/// Names the virtual file of a parsed macro call after the macro and the string literal it starts with, like `env!("PORT")`.
+/// Falls back to the macro name alone if the call doesn't start with a string literal.
+fn virtual_file_name(db: &SimpleParserDatabase, macro_name: &str, node: &Expr) -> String {
+    let Expr::InlineMacro(mac) = node else {
+        return macro_name.to_string();
+    };
+    match inline_macro_args(db, mac)
+        .first()
+        .and_then(|arg| get_env_variable_name(db, &arg.arg_clause(db.upcast())).ok())
+    {
+        Some(name) => format!("{}({})", macro_name, string_literal(name.as_bytes())),
+        None => macro_name.to_string(),
+    }
+}
+
+/// Returns the diagnostic error for an environment variable that is not set, located at the name argument.
+/// The message ends with a help note suggesting to set the variable, or to pass a default if the macro given in
+/// `default_hint` as its name and an example default accepts one.
+pub(crate) fn not_set_error(
+    db: &SimpleParserDatabase,
+    name_arg: &Arg,
+    env_var_name: &str,
+    default_hint: Option<(&str, &str)>,
+) -> Diagnostic {
+    let mut diagnostic = error_at(
+        db,
+        name_arg,
+        format!("Environment variable {} not set", env_var_name),
+    );
+    diagnostic.message.push_str(&match default_hint {
+        Some((macro_name, default_example)) => format!(
+            "\nhelp: set {} or pass a default like {}(\"{}\", {})",
+            env_var_name, macro_name, env_var_name, default_example
+        ),
+        None => format!(
+            "\nhelp: set {} in the environment or a .env file",
+            env_var_name
+        ),
+    });
+    diagnostic
+}
+
+/// Returns the diagnostic error for a variable that is set in the process environment to a value that is not valid UTF-8,
+/// located at the name argument.
+pub(crate) fn non_unicode_error(
+    db: &SimpleParserDatabase,
+    name_arg: &Arg,
+    env_var_name: &str,
+) -> Diagnostic {
+    error_at(db, name_arg, non_unicode_message(env_var_name))
+}
+
+/// Returns the message of the diagnostic error for a variable that is set to a value that is not valid UTF-8.
+pub(crate) fn non_unicode_message(env_var_name: &str) -> String {
+    format!("{} is set but not valid UTF-8", env_var_name)
+}
+
+/// Creates a diagnostic error about the given syntax node.
+/// Procedural macro diagnostics carry no location in `cairo-lang-macro` 0.1, so the diagnostic can't span the node,
+/// and the text of the node is quoted in a note instead to name the offending argument within the macro call.
+pub(crate) fn error_at(
+    db: &SimpleParserDatabase,
+    node: &impl TypedSyntaxNode,
+    message: impl ToString,
+) -> Diagnostic {
+    Diagnostic::error(format!(
+        "{}\nnote: in `{}`",
+        message.to_string(),
+        syntax_text(db, node)
+    ))
+}
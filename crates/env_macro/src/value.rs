@@ -0,0 +1,618 @@
+// SPDX-License-Identifier: MIT
+// Based on the code from Alexandria library (https://github.com/keep-starknet-strange/alexandria)
+// Copyright (c) 2025 Alexandria Contributors
+
+use std::str::FromStr;
+
+use cairo_lang_macro::Diagnostic;
+use num_bigint::{BigInt, Sign};
+
+use crate::options::ValueKind;
+
+/// Value of an environment variable or a default value.
+#[derive(Clone)]
+pub(crate) enum EnvValue {
+    /// A number, expanded to a numeric literal.
+    Numeric(BigInt),
+    /// A string, expanded to a `ByteArray` literal.
+    String(String),
+    /// A boolean, expanded to a `bool` literal.
+    Bool(bool),
+    /// An arbitrary expression given as a default, expanded verbatim.
+    Expr(String),
+}
+
+impl EnvValue {
+    /// Formats the value as a Cairo literal.
+    pub(crate) fn to_literal(&self) -> String {
+        match self {
+            EnvValue::Numeric(numeric_val) => numeric_val.to_string(),
+            EnvValue::String(string_val) => string_literal(string_val.as_bytes()),
+            EnvValue::Bool(bool_val) => bool_val.to_string(),
+            EnvValue::Expr(expr) => expr.clone(),
+        }
+    }
+}
+
+/// Parses the value of an environment variable as a number if possible, or as a string otherwise.
+/// Surrounding whitespace is ignored when parsing numbers but preserved in strings.
+pub(crate) fn parse_env_value(env_var_name: &str, val: &str) -> Result<EnvValue, Diagnostic> {
+    match parse_numeric_value(env_var_name, val.trim())? {
+        Some(numeric_val) => Ok(EnvValue::Numeric(numeric_val)),
+        None => Ok(EnvValue::String(val.to_string())),
+    }
+}
+
+/// Largest bit width accepted as `width:`, the width of `u256`.
+pub(crate) const MAX_HEX_WIDTH: u32 = 256;
+
+/// Formats a numeric value as a hexadecimal literal, like `0x2a`.
+/// With a bit width, a negative value is formatted as its two's complement at that width, e.g. `-1` at 8 bits as `0xff`.
+/// Returns a diagnostic error naming the variable if the value is negative without a width or doesn't fit into the width.
+pub(crate) fn hex_literal(
+    env_var_name: &str,
+    numeric_val: &BigInt,
+    width: Option<u32>,
+) -> Result<String, Diagnostic> {
+    let Some(width) = width else {
+        if numeric_val.sign() == Sign::Minus {
+            return Err(Diagnostic::error(format!(
+                "{}={} is negative and can't be expanded as hex, pass width: to use two's complement",
+                env_var_name, numeric_val
+            )));
+        }
+        return Ok(format!("0x{}", numeric_val.to_str_radix(16)));
+    };
+
+    // Both the signed and the unsigned range of the width are accepted.
+    let modulus = BigInt::from(1) << width;
+    let min = -(BigInt::from(1) << (width - 1));
+    if *numeric_val < min || *numeric_val >= modulus {
+        return Err(Diagnostic::error(format!(
+            "{}={} does not fit into {} bits ({}..={})",
+            env_var_name,
+            numeric_val,
+            width,
+            min,
+            &modulus - 1
+        )));
+    }
+    let unsigned_val = if numeric_val.sign() == Sign::Minus {
+        numeric_val + modulus
+    } else {
+        numeric_val.clone()
+    };
+    Ok(format!("0x{}", unsigned_val.to_str_radix(16)))
+}
+
+/// Parses the value of an environment variable as the requested kind of literal.
+/// Returns a diagnostic error if the value can't be interpreted as that kind.
+pub(crate) fn parse_env_value_as(
+    env_var_name: &str,
+    val: &str,
+    kind: ValueKind,
+) -> Result<EnvValue, Diagnostic> {
+    match kind {
+        // Values that look numeric but are malformed keep the reason from the numeric parser.
+        // Boolean-like values, like `true` or `off`, are coerced to 1 and 0 when a felt is requested.
+        ValueKind::Felt | ValueKind::Hex => parse_numeric_value(env_var_name, val.trim())?
+            .or_else(|| {
+                matches!(kind, ValueKind::Felt)
+                    .then(|| parse_bool_value(val.trim()))
+                    .flatten()
+                    .map(BigInt::from)
+            })
+            .map(EnvValue::Numeric)
+            .ok_or_else(|| {
+                Diagnostic::error(format!(
+                    "{} is not numeric (kind: {} requested): {}",
+                    env_var_name,
+                    kind.name(),
+                    truncate_value(val)
+                ))
+            }),
+        ValueKind::ByteArray => Ok(EnvValue::String(val.to_string())),
+        ValueKind::Bool => parse_bool_value(val.trim()).map(EnvValue::Bool).ok_or_else(|| {
+            Diagnostic::error(format!(
+                "Failed to parse boolean value of environment variable {}: {} (expected one of true/false, 1/0, yes/no, on/off)",
+                env_var_name,
+                truncate_value(val)
+            ))
+        }),
+    }
+}
+
+/// Parses the value of an environment variable as a boolean.
+/// Returns `None` if the value is not one of the accepted boolean forms.
+pub(crate) fn parse_bool_value(val: &str) -> Option<bool> {
+    match val.to_ascii_lowercase().as_str() {
+        "true" | "1" | "yes" | "on" => Some(true),
+        "false" | "0" | "no" | "off" => Some(false),
+        _ => None,
+    }
+}
+
+/// Units accepted at the end of a duration, with their length in seconds.
+const DURATION_UNITS: [(&str, u32); 4] = [("s", 1), ("m", 60), ("h", 60 * 60), ("d", 24 * 60 * 60)];
+
+/// Parses the value of an environment variable as a duration, like `30s`, `5m` or `1h`.
+/// A number without a unit is in seconds.
+/// Returns the duration in seconds or a diagnostic error if the value is negative or has an unknown unit.
+pub(crate) fn parse_duration_value(env_var_name: &str, val: &str) -> Result<BigInt, Diagnostic> {
+    let trimmed = val.trim();
+    if trimmed.starts_with('-') {
+        return Err(Diagnostic::error(format!(
+            "Duration of environment variable {} must not be negative: {}",
+            env_var_name,
+            truncate_value(val)
+        )));
+    }
+
+    let unit_start = trimmed
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(trimmed.len());
+    let (amount, unit) = trimmed.split_at(unit_start);
+    let amount = BigInt::parse_bytes(amount.as_bytes(), 10).ok_or_else(|| {
+        Diagnostic::error(format!(
+            "Failed to parse duration of environment variable {}: {}",
+            env_var_name,
+            truncate_value(val)
+        ))
+    })?;
+
+    let unit = unit.trim();
+    if unit.is_empty() {
+        return Ok(amount);
+    }
+    let (_, unit_seconds) = DURATION_UNITS
+        .iter()
+        .find(|(name, _)| *name == unit)
+        .ok_or_else(|| {
+            let units: Vec<&str> = DURATION_UNITS.iter().map(|(name, _)| *name).collect();
+            Diagnostic::error(format!(
+                "Unrecognized duration unit {} in environment variable {}, expected one of {}",
+                truncate_value(unit),
+                env_var_name,
+                units.join(", ")
+            ))
+        })?;
+    Ok(amount * unit_seconds)
+}
+
+/// Parses the value of an environment variable as an IPv4 socket address, like `127.0.0.1:8080`.
+/// Returns the four octets and the port or a diagnostic error describing the malformed part.
+pub(crate) fn parse_socketaddr_value(
+    env_var_name: &str,
+    val: &str,
+) -> Result<([u8; 4], u16), Diagnostic> {
+    let socketaddr_error = |reason: String| {
+        Diagnostic::error(format!(
+            "Invalid socket address in environment variable {}: {} ({})",
+            env_var_name,
+            truncate_value(val),
+            reason
+        ))
+    };
+
+    let (host, port) = val
+        .trim()
+        .rsplit_once(':')
+        .filter(|(_, port)| !port.is_empty())
+        .ok_or_else(|| socketaddr_error("missing port".to_string()))?;
+    let octets: Vec<&str> = host.split('.').collect();
+    let [a, b, c, d] = octets.as_slice() else {
+        return Err(socketaddr_error(format!(
+            "expected 4 octets, got {}",
+            octets.len()
+        )));
+    };
+
+    let mut parsed_octets = [0u8; 4];
+    for (parsed, octet) in parsed_octets.iter_mut().zip([a, b, c, d]) {
+        *parsed = parse_decimal(octet).ok_or_else(|| {
+            socketaddr_error(format!("octet {} is not a number between 0 and 255", octet))
+        })?;
+    }
+    let port = parse_decimal(port).ok_or_else(|| {
+        socketaddr_error(format!("port {} is not a number between 0 and 65535", port))
+    })?;
+    Ok((parsed_octets, port))
+}
+
+/// Parses a plain decimal number, without signs or separators.
+/// Returns `None` if the value is not a number or doesn't fit into the target type.
+fn parse_decimal<T: FromStr>(val: &str) -> Option<T> {
+    if val.is_empty() || !val.bytes().all(|byte| byte.is_ascii_digit()) {
+        return None;
+    }
+    val.parse().ok()
+}
+
+/// Parses the value of an environment variable as a hex color, like `#1a2b3c` or `#1a2b3cff` with an alpha channel.
+/// Returns the color packed into a number or a diagnostic error if the value has a wrong length or non-hex digits.
+pub(crate) fn parse_color_value(env_var_name: &str, val: &str) -> Result<u64, Diagnostic> {
+    let trimmed = val.trim();
+    let digits = trimmed.strip_prefix('#').unwrap_or(trimmed);
+    if !matches!(digits.len(), 6 | 8) || !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return Err(Diagnostic::error(format!(
+            "Failed to parse color value of environment variable {}: {} (expected #RRGGBB or #RRGGBBAA)",
+            env_var_name,
+            truncate_value(val)
+        )));
+    }
+    u64::from_str_radix(digits, 16).map_err(|_| numeric_parse_error(env_var_name, val))
+}
+
+/// Parses the value of an environment variable as a semantic version, like `1.2.3`, `1.2` or `1.2.3-rc.1+build.5`.
+/// Returns the major, minor and patch components, missing ones set to zero, or a diagnostic error describing the malformed part.
+pub(crate) fn parse_semver_value(env_var_name: &str, val: &str) -> Result<[BigInt; 3], Diagnostic> {
+    let semver_error = |reason: String| {
+        Diagnostic::error(format!(
+            "Invalid semantic version in environment variable {}: {} ({})",
+            env_var_name,
+            truncate_value(val),
+            reason
+        ))
+    };
+
+    let trimmed = val.trim();
+    let core = trimmed.split(['-', '+']).next().unwrap_or_default();
+    let components: Vec<&str> = core.split('.').collect();
+    if components.len() > 3 {
+        return Err(semver_error(format!(
+            "expected at most 3 components, got {}",
+            components.len()
+        )));
+    }
+
+    let mut version: [BigInt; 3] = Default::default();
+    for (parsed, component) in version.iter_mut().zip(components) {
+        *parsed = parse_decimal(component)
+            .ok_or_else(|| semver_error(format!("component {:?} is not a number", component)))?;
+    }
+    Ok(version)
+}
+
+/// Lengths of the hyphen-separated groups of hex digits in a canonical UUID.
+const UUID_GROUP_LENGTHS: [usize; 5] = [8, 4, 4, 4, 12];
+
+/// Parses the value of an environment variable as a canonical UUID, like `123e4567-e89b-12d3-a456-426614174000`.
+/// Returns the 16 bytes of the UUID as a big-endian number or a diagnostic error with the offending value.
+pub(crate) fn parse_uuid_value(env_var_name: &str, val: &str) -> Result<Vec<u8>, Diagnostic> {
+    let trimmed = val.trim();
+    let groups: Vec<&str> = trimmed.split('-').collect();
+    let is_canonical = groups.len() == UUID_GROUP_LENGTHS.len()
+        && groups.iter().zip(UUID_GROUP_LENGTHS).all(|(group, len)| {
+            group.len() == len && group.bytes().all(|byte| byte.is_ascii_hexdigit())
+        });
+    if !is_canonical {
+        return Err(Diagnostic::error(format!(
+            "Failed to parse UUID value of environment variable {}: {} (expected the form xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx)",
+            env_var_name,
+            truncate_value(val)
+        )));
+    }
+    let digits = groups.concat();
+    // A UUID is 128 bits, so it always fits into a felt252.
+    Ok((0..digits.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&digits[i..i + 2], 16).unwrap_or_default())
+        .collect())
+}
+
+/// Number of basis points in 100%.
+pub(crate) const MAX_BPS: u32 = 10_000;
+
+/// Parses the value of an environment variable as a percentage, like `2.5%`, and converts it to basis points.
+/// Returns the basis points or a diagnostic error if the value has more than two decimal places or exceeds 100%.
+pub(crate) fn parse_bps_value(env_var_name: &str, val: &str) -> Result<BigInt, Diagnostic> {
+    let parse_error = || {
+        Diagnostic::error(format!(
+            "Failed to parse percentage of environment variable {}: {}",
+            env_var_name,
+            truncate_value(val)
+        ))
+    };
+
+    let trimmed = val.trim();
+    let percentage = trimmed.strip_suffix('%').unwrap_or(trimmed).trim_end();
+    let (whole, fraction) = percentage.split_once('.').unwrap_or((percentage, ""));
+    let is_digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+    if whole.is_empty() || !is_digits(whole) || !is_digits(fraction) || percentage.ends_with('.') {
+        return Err(parse_error());
+    }
+    if fraction.len() > 2 {
+        return Err(Diagnostic::error(format!(
+            "Percentage of environment variable {} has more than two decimal places: {}",
+            env_var_name,
+            truncate_value(val)
+        )));
+    }
+
+    let whole = BigInt::parse_bytes(whole.as_bytes(), 10).ok_or_else(parse_error)?;
+    let fraction: u32 = format!("{:0<2}", fraction)
+        .parse()
+        .map_err(|_| parse_error())?;
+    let bps = whole * 100 + fraction;
+    if !is_bps_in_range(&bps) {
+        return Err(Diagnostic::error(format!(
+            "{}={} exceeds 100%",
+            env_var_name,
+            truncate_value(trimmed)
+        )));
+    }
+    Ok(bps)
+}
+
+/// Checks that a number of basis points is between 0% and 100%.
+pub(crate) fn is_bps_in_range(bps: &BigInt) -> bool {
+    *bps >= BigInt::ZERO && *bps <= BigInt::from(MAX_BPS)
+}
+
+/// Parses the value of an environment variable as a decimal number, like `12.34` or `-0.5`, and multiplies it by `10^scale`.
+/// Returns the scaled integer or a diagnostic error if the value is not a decimal or has more decimal places than the scale.
+pub(crate) fn parse_fixed_value(
+    env_var_name: &str,
+    val: &str,
+    scale: u32,
+) -> Result<BigInt, Diagnostic> {
+    let parse_error = || {
+        Diagnostic::error(format!(
+            "Failed to parse decimal value of environment variable {}: {}",
+            env_var_name,
+            truncate_value(val)
+        ))
+    };
+
+    let trimmed = val.trim();
+    let (negative, decimal) = match trimmed.strip_prefix('-') {
+        Some(decimal) => (true, decimal),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+    let (whole, fraction) = decimal.split_once('.').unwrap_or((decimal, ""));
+    let is_digits = |part: &str| part.bytes().all(|byte| byte.is_ascii_digit());
+    if whole.is_empty() || !is_digits(whole) || !is_digits(fraction) || decimal.ends_with('.') {
+        return Err(parse_error());
+    }
+    if fraction.len() > scale as usize {
+        return Err(Diagnostic::error(format!(
+            "Decimal value of environment variable {} has more than {} decimal places: {}",
+            env_var_name,
+            scale,
+            truncate_value(val)
+        )));
+    }
+
+    let digits = format!("{}{:0<width$}", whole, fraction, width = scale as usize);
+    let fixed = BigInt::parse_bytes(digits.as_bytes(), 10).ok_or_else(parse_error)?;
+    Ok(if negative { -fixed } else { fixed })
+}
+
+/// Radix prefixes supported in numeric environment variable values.
+pub(crate) const RADIX_PREFIXES: [(&str, u32); 3] = [("0x", 16), ("0o", 8), ("0b", 2)];
+
+/// Parses the value of an environment variable as a number.
+/// Hexadecimal, octal and binary values must have the `0x`, `0o` and `0b` prefix respectively,
+/// otherwise the value is parsed as a decimal.
+/// Digits can be separated with underscores, e.g. `1_000_000`, and decimals can use integer scientific notation, e.g. `1e6`.
+/// Returns `None` if the value is not numeric and should be treated as a string,
+/// or a diagnostic error if the value looks numeric but has invalid digits.
+pub(crate) fn parse_numeric_value(
+    env_var_name: &str,
+    val: &str,
+) -> Result<Option<BigInt>, Diagnostic> {
+    let bytes = val.as_bytes();
+    for (prefix, radix) in RADIX_PREFIXES {
+        // Prefixes are case-insensitive, e.g. both `0x` and `0X` are accepted.
+        if bytes.len() >= prefix.len()
+            && bytes[..prefix.len()].eq_ignore_ascii_case(prefix.as_bytes())
+        {
+            return strip_digit_separators(&val[prefix.len()..], radix)
+                .and_then(|digits| BigInt::parse_bytes(digits.as_bytes(), radix))
+                .map(Some)
+                .ok_or_else(|| numeric_parse_error(env_var_name, val));
+        }
+    }
+
+    if let Some(numeric_val) = parse_scientific_value(env_var_name, val)? {
+        return Ok(Some(numeric_val));
+    }
+
+    if !val.contains('_') {
+        return Ok(BigInt::from_str(val).ok());
+    }
+
+    // Values with underscores are only numeric if they consist of digits and separators,
+    // otherwise they are regular strings like `MY_VALUE`.
+    let digits = val.strip_prefix('-').unwrap_or(val);
+    if !digits.bytes().all(|b| b.is_ascii_digit() || b == b'_')
+        || !digits.bytes().any(|b| b.is_ascii_digit())
+    {
+        return Ok(None);
+    }
+
+    strip_digit_separators(val, 10)
+        .and_then(|digits| BigInt::from_str(&digits).ok())
+        .map(Some)
+        .ok_or_else(|| numeric_parse_error(env_var_name, val))
+}
+
+/// Largest exponent accepted in scientific notation, well beyond the `u256` range.
+const MAX_SCIENTIFIC_EXPONENT: u32 = 100;
+
+/// Parses a value in integer scientific notation, like `1e6` or `1.5e1`, expanding it to the full integer.
+/// Returns `None` if the value is not in scientific notation,
+/// or a diagnostic error if the exponent is negative or too large or the value is not an integer.
+fn parse_scientific_value(env_var_name: &str, val: &str) -> Result<Option<BigInt>, Diagnostic> {
+    let Some((mantissa, exponent)) = val.split_once(['e', 'E']) else {
+        return Ok(None);
+    };
+    let (negative, unsigned_mantissa) = match mantissa.strip_prefix('-') {
+        Some(unsigned_mantissa) => (true, unsigned_mantissa),
+        None => (false, mantissa),
+    };
+    let (whole, fraction) = unsigned_mantissa
+        .split_once('.')
+        .unwrap_or((unsigned_mantissa, ""));
+    let exponent_digits = exponent.strip_prefix(['+', '-']).unwrap_or(exponent);
+    let is_digits = |part: &str| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit());
+    if !is_digits(whole)
+        || !(fraction.is_empty() || is_digits(fraction))
+        || unsigned_mantissa.ends_with('.')
+        || !is_digits(exponent_digits)
+    {
+        return Ok(None);
+    }
+
+    let scientific_error = |reason: &str| {
+        Diagnostic::error(format!(
+            "Failed to parse numeric value of environment variable {}: {} ({})",
+            env_var_name,
+            truncate_value(val),
+            reason
+        ))
+    };
+    if exponent.starts_with('-') {
+        return Err(scientific_error("negative exponents are not supported"));
+    }
+    let exponent = exponent_digits
+        .parse::<u32>()
+        .ok()
+        .filter(|exponent| *exponent <= MAX_SCIENTIFIC_EXPONENT)
+        .ok_or_else(|| scientific_error("exponent is too large"))?;
+
+    // Shift the decimal point of the mantissa by the exponent, the digits left after it must be zeros.
+    let digits = format!("{}{}", whole, fraction);
+    let integer_len = whole.len() + exponent as usize;
+    let integer_digits = if integer_len >= digits.len() {
+        format!("{:0<width$}", digits, width = integer_len)
+    } else {
+        if digits[integer_len..].bytes().any(|b| b != b'0') {
+            return Err(scientific_error("not an integer"));
+        }
+        digits[..integer_len].to_string()
+    };
+    let numeric_val =
+        BigInt::from_str(&integer_digits).map_err(|_| numeric_parse_error(env_var_name, val))?;
+    Ok(Some(if negative { -numeric_val } else { numeric_val }))
+}
+
+/// Removes underscore digit separators from a numeric value.
+/// Returns `None` if an underscore is not placed between two digits (leading, trailing or doubled).
+pub(crate) fn strip_digit_separators(val: &str, radix: u32) -> Option<String> {
+    let chars: Vec<char> = val.chars().collect();
+    let mut digits = String::with_capacity(val.len());
+    for (i, &c) in chars.iter().enumerate() {
+        if c != '_' {
+            digits.push(c);
+            continue;
+        }
+        let after_digit = i > 0 && chars[i - 1].is_digit(radix);
+        let before_digit = chars.get(i + 1).is_some_and(|next| next.is_digit(radix));
+        if !after_digit || !before_digit {
+            return None;
+        }
+    }
+    Some(digits)
+}
+
+/// Maximum number of characters of a value shown in diagnostics.
+const MAX_DIAGNOSTIC_VALUE_LEN: usize = 64;
+
+/// Returns the diagnostic error for a numeric value that failed to parse, with the reason if it can be pinpointed.
+pub(crate) fn numeric_parse_error(env_var_name: &str, val: &str) -> Diagnostic {
+    let reason = numeric_error_reason(val.trim())
+        .map(|reason| format!(" ({})", reason))
+        .unwrap_or_default();
+    Diagnostic::error(format!(
+        "Failed to parse numeric value of environment variable {}: {}{}",
+        env_var_name,
+        truncate_value(val),
+        reason
+    ))
+}
+
+/// Describes why a value is not a valid number, like "invalid digit `g` at position 4".
+/// Positions count characters from 1, including the sign and radix prefix.
+/// Returns `None` if no single offending character can be found.
+fn numeric_error_reason(val: &str) -> Option<String> {
+    let sign_len = usize::from(val.starts_with(['-', '+']));
+    let (prefix_len, radix) = RADIX_PREFIXES
+        .iter()
+        .find(|(prefix, _)| {
+            val.get(..prefix.len())
+                .is_some_and(|start| start.eq_ignore_ascii_case(prefix))
+        })
+        .map_or((sign_len, 10), |(prefix, radix)| (prefix.len(), *radix));
+
+    let chars: Vec<char> = val.chars().skip(prefix_len).collect();
+    if chars.is_empty() {
+        return Some("no digits".to_string());
+    }
+    for (i, &c) in chars.iter().enumerate() {
+        let position = prefix_len + i + 1;
+        if c == '_' {
+            let after_digit = i > 0 && chars[i - 1].is_digit(radix);
+            let before_digit = chars.get(i + 1).is_some_and(|next| next.is_digit(radix));
+            if !after_digit || !before_digit {
+                return Some(format!(
+                    "misplaced digit separator at position {}",
+                    position
+                ));
+            }
+        } else if !c.is_digit(radix) {
+            return Some(format!("invalid digit `{}` at position {}", c, position));
+        }
+    }
+    None
+}
+
+/// Truncates a value longer than [`MAX_DIAGNOSTIC_VALUE_LEN`] characters with an ellipsis, to keep diagnostics readable.
+pub(crate) fn truncate_value(val: &str) -> String {
+    if val.chars().count() > MAX_DIAGNOSTIC_VALUE_LEN {
+        let truncated: String = val.chars().take(MAX_DIAGNOSTIC_VALUE_LEN).collect();
+        format!("{}...", truncated)
+    } else {
+        val.to_string()
+    }
+}
+
+/// Formats ASCII bytes as a Cairo short string literal, like `'abc'`, escaping quotes, backslashes and control characters.
+pub(crate) fn short_string_literal(bytes: &[u8]) -> String {
+    let mut literal = String::with_capacity(bytes.len() + 2);
+    literal.push('\'');
+    for &byte in bytes {
+        match byte {
+            b'\'' => literal.push_str("\\'"),
+            b'\\' => literal.push_str("\\\\"),
+            b'\n' => literal.push_str("\\n"),
+            b'\r' => literal.push_str("\\r"),
+            b'\t' => literal.push_str("\\t"),
+            0x20..=0x7e => literal.push(byte as char),
+            _ => literal.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    literal.push('\'');
+    literal
+}
+
+/// Formats the bytes of a string as a Cairo `ByteArray` literal.
+/// Quotes, backslashes and control characters are escaped, non-ASCII bytes are emitted as `\x` escapes.
+pub(crate) fn string_literal(bytes: &[u8]) -> String {
+    let mut literal = String::with_capacity(bytes.len() + 2);
+    literal.push('"');
+    for &byte in bytes {
+        match byte {
+            b'"' => literal.push_str("\\\""),
+            b'\\' => literal.push_str("\\\\"),
+            b'\n' => literal.push_str("\\n"),
+            b'\r' => literal.push_str("\\r"),
+            b'\t' => literal.push_str("\\t"),
+            b'\0' => literal.push_str("\\0"),
+            0x20..=0x7e => literal.push(byte as char),
+            _ => literal.push_str(&format!("\\x{:02x}", byte)),
+        }
+    }
+    literal.push('"');
+    literal
+}
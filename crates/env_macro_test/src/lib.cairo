@@ -1,6 +1,20 @@
 const VERSION: usize = env!("VERSION", 1);
+const OFFSET: i32 = env!("OFFSET", -5);
+const SLOT: u64 = env!("SLOT", 60 * 60);
+
+#[env_consts("ENV_MACRO_TEST_")]
+mod config {}
 
 #[executable]
 fn main() {
     assert(VERSION == 2, 'VERSION is not 2');
+    assert(OFFSET == -5, 'OFFSET is not -5');
+    assert(SLOT == 3600, 'SLOT is not 3600');
+    assert(config::PORT == 8080, 'config::PORT is not 8080');
+
+    let offset: i32 = env!("OFFSET", -5) * 2;
+    assert(offset == -10, 'offset is not -10');
+
+    let name: ByteArray = env!("NAME");
+    assert(name == "env_macro", 'NAME is not env_macro');
 }